@@ -0,0 +1,106 @@
+//! Times rounds of a crowded game, and compares finding collisions through the occupancy
+//! index against checking every pair of snakes. Run with `cargo bench`.
+
+extern crate rand;
+extern crate sirpent;
+
+use rand::XorShiftRng;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use sirpent::engine::*;
+use sirpent::state::*;
+
+const PLAYERS: usize = 100;
+const RADIUS: usize = 50;
+const ROUNDS: usize = 200;
+const REPEATS: u32 = 100;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+// Boards wrap around where the tiling supports it, so that snakes don't run off the edge.
+#[cfg(feature = "hexagon")]
+fn board() -> Grid {
+    Grid::toroidal(RADIUS)
+}
+
+#[cfg(not(feature = "hexagon"))]
+fn board() -> Grid {
+    Grid::new(RADIUS)
+}
+
+fn crowded_game() -> Game {
+    let mut rules = Rules::default();
+    rules.spawns.initial_length = 10;
+    rules.food.count = PLAYERS;
+    let mut game = Game::with_rules(Box::new(XorShiftRng::new_unseeded()),
+                                    board(),
+                                    Map::default(),
+                                    rules);
+    for _ in 0..PLAYERS {
+        game.add_player("player".to_string()).unwrap();
+    }
+    game.next(Event::Start).unwrap();
+    game
+}
+
+fn main() {
+    let game = crowded_game();
+    let round_state = game.round_state();
+    let grid = board();
+    let snakes: Vec<(&String, &Snake)> = round_state.snakes.iter().collect();
+
+    let start = Instant::now();
+    let mut pairwise = 0;
+    for _ in 0..REPEATS {
+        pairwise = snakes
+            .iter()
+            .filter(|&&(_, snake)| {
+                        snakes
+                            .iter()
+                            .any(|&(_, other)| {
+                                     snake != other && snake.has_collided_into(other, &grid)
+                                 })
+                    })
+            .count();
+    }
+    let pairwise_time = millis(start.elapsed()) * 1000.0 / REPEATS as f64;
+
+    let start = Instant::now();
+    let mut indexed = 0;
+    for _ in 0..REPEATS {
+        indexed = snakes
+            .iter()
+            .filter(|&&(name, snake)| {
+                        round_state
+                            .occupancy
+                            .owners(&snake.segments[0])
+                            .iter()
+                            .any(|owner| owner != name)
+                    })
+            .count();
+    }
+    let indexed_time = millis(start.elapsed()) * 1000.0 / REPEATS as f64;
+
+    assert_eq!(pairwise, indexed);
+    println!("collisions among {} snakes: pairwise {:.1} us, occupancy index {:.1} us",
+             snakes.len(),
+             pairwise_time,
+             indexed_time);
+
+    let mut game = crowded_game();
+    let start = Instant::now();
+    let mut rounds = 0;
+    while rounds < ROUNDS && game.state() == &State::Running {
+        // Every snake carries on the way it is facing.
+        let moves: HashMap<String, Direction> = game.round_state().directions.clone();
+        game.next(Event::Turn(moves)).unwrap();
+        rounds += 1;
+    }
+    println!("{} rounds of {} players: {:.3} ms per round",
+             rounds,
+             PLAYERS,
+             millis(start.elapsed()) / rounds as f64);
+}
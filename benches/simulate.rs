@@ -0,0 +1,51 @@
+//! Counts how many rounds bots can simulate ahead on a small grid, as a search would.
+//! Run with `cargo bench`.
+
+extern crate rand;
+extern crate sirpent;
+
+use rand::XorShiftRng;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use sirpent::engine::*;
+use sirpent::state::*;
+
+const PLAYERS: usize = 4;
+const RADIUS: usize = 5;
+const SIMULATIONS: usize = 100_000;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+fn main() {
+    let mut game = Game::new(Box::new(XorShiftRng::new_unseeded()), Grid::new(RADIUS));
+    for i in 0..PLAYERS {
+        game.add_player(format!("player{}", i)).unwrap();
+    }
+    game.next(Event::Start).unwrap();
+    let moves: HashMap<String, Direction> = game.round_state().directions.clone();
+
+    let start = Instant::now();
+    let mut survivors = 0;
+    for _ in 0..SIMULATIONS {
+        survivors += game.simulate(moves.clone()).snakes.len();
+    }
+    let elapsed = millis(start.elapsed());
+    println!("simulate: {:.0} rounds per ms ({} survivors)",
+             SIMULATIONS as f64 / elapsed,
+             survivors);
+
+    game.set_deterministic(true);
+    let start = Instant::now();
+    for _ in 0..SIMULATIONS {
+        let mut child = game.clone();
+        child.next(Event::Turn(moves.clone())).unwrap();
+        survivors += child.round_state().snakes.len();
+    }
+    let elapsed = millis(start.elapsed());
+    println!("deterministic: {:.0} rounds per ms ({} survivors)",
+             SIMULATIONS as f64 / elapsed,
+             survivors);
+}
@@ -1,29 +1,116 @@
-use futures::{future, Future};
+use futures::{future, stream, Future};
+use futures::future::Either;
 use tokio_timer;
 use kabuki::Actor;
+use std::time::{Duration, Instant};
 use std::collections::{HashMap, HashSet};
-use futures::sync::mpsc;
+use futures::sync::{mpsc, oneshot};
 use futures::Sink;
+use comms::{Client, Room};
 
 use net::*;
 use state::*;
 use engine::*;
+use errors::ErrorKind;
 use utils::*;
+use super::nameserver::NameReleaser;
+
+/// How often to check for reconnections while waiting on a dropped player.
+const RECONNECTION_POLL_MILLIS: u64 = 100;
 
 #[derive(Clone)]
 pub struct GameActor {
     timer: tokio_timer::Timer,
     spectator_tx: mpsc::Sender<Msg>,
+    resumptions: Resumptions,
+    arrivals: Arrivals,
+}
+
+/// Where players joining a running game come from, the teams they registered for, where
+/// players leaving an arena go back to, and where to give back the names of any that can't
+/// be added.
+#[derive(Clone)]
+struct Arrivals {
+    lobby_tx: mpsc::Sender<MsgClient<String>>,
+    lobby_request_tx: mpsc::Sender<LobbyRequest>,
+    roster: TeamRoster,
+    releaser: NameReleaser,
 }
 
 impl GameActor {
-    pub fn new(timer: tokio_timer::Timer, spectator_tx: mpsc::Sender<Msg>) -> GameActor {
+    pub fn new(timer: tokio_timer::Timer,
+               spectator_tx: mpsc::Sender<Msg>,
+               resumptions: Resumptions,
+               roster: TeamRoster,
+               lobby_tx: mpsc::Sender<MsgClient<String>>,
+               lobby_request_tx: mpsc::Sender<LobbyRequest>,
+               releaser: NameReleaser)
+               -> GameActor {
         GameActor {
             timer: timer,
             spectator_tx: spectator_tx,
+            resumptions: resumptions,
+            arrivals: Arrivals {
+                lobby_tx: lobby_tx,
+                lobby_request_tx: lobby_request_tx,
+                roster: roster,
+                releaser: releaser,
+            },
         }
     }
 
+    /// Swaps in any reconnected clients and drops the ones whose connection has gone,
+    /// releasing the hold each of those had on its name.
+    fn reattach(players: MsgRoom<String>,
+                living_player_ids: &HashSet<String>,
+                resumptions: &Resumptions,
+                releaser: &NameReleaser)
+                -> MsgRoom<String> {
+        let mut clients: HashMap<String, MsgClient<String>> = HashMap::new();
+        for client in players {
+            if client.is_connected() {
+                clients.insert(client.id(), client);
+            } else {
+                let _ = releaser.send(client.id());
+            }
+        }
+        for client in resumptions.take(living_player_ids) {
+            clients.insert(client.id(), client);
+        }
+        Room::new(clients.into_iter().map(|(_, client)| client).collect())
+    }
+
+    /// Waits for living players whose connection dropped to resume their session, so that a
+    /// brief blip doesn't cost them their snake. Each has the move timeout from when their
+    /// connection was found to have dropped, and rounds aren't held for them after that.
+    fn await_reconnections(players: MsgRoom<String>,
+                           living_player_ids: HashSet<String>,
+                           timeout: Milliseconds,
+                           timer: tokio_timer::Timer,
+                           resumptions: Resumptions,
+                           releaser: NameReleaser)
+                           -> Box<Future<Item = MsgRoom<String>, Error = ()>> {
+        let timeout: Duration = timeout.into();
+        let future = future::loop_fn(players, move |players| {
+            let players = Self::reattach(players, &living_player_ids, &resumptions, &releaser);
+            let present = players.ids();
+            let now = Instant::now();
+            let waiting = living_player_ids
+                .iter()
+                .filter(|id| !present.contains(*id))
+                .any(|id| resumptions.dropped(id, timeout) > now);
+            if !waiting {
+                Either::A(future::ok(future::Loop::Break(players)))
+            } else {
+                let poll_interval = Duration::from_millis(RECONNECTION_POLL_MILLIS);
+                Either::B(timer
+                              .sleep(poll_interval)
+                              .then(move |_| Ok(future::Loop::Continue(players))))
+            }
+        });
+        Box::new(future)
+    }
+
     fn broadcast(msg: Msg,
                  players: MsgRoom<String>,
                  spectator_tx: mpsc::Sender<Msg>)
@@ -33,37 +120,385 @@ impl GameActor {
         Box::new(tx_to_players.join(tx_to_spectators).map_err(|_| ()))
     }
 
-    fn receive_move_direction
+    /// Receives a message other than `pong` from each of the given players, timing how long
+    /// each one took to reply. Players who don't reply within the timeout make no move but
+    /// stay connected. Forfeits the polled players have already sent are picked up too,
+    /// without waiting on them.
+    fn receive_moves
         (players: MsgRoom<String>,
-         living_player_ids: HashSet<String>,
+         player_ids: HashSet<String>,
+         polled_ids: HashSet<String>,
          timeout: Milliseconds,
          timer: &tokio_timer::Timer)
-         -> Box<Future<Item = (HashMap<String, Direction>, MsgRoom<String>), Error = ()>> {
-        let future = players
-            .receive(living_player_ids)
-            //.with_soft_timeout(timeout, timer) // @TODO
-            .map(|(msgs, players)| {
-                (msgs_to_directions(msgs), players)
+         -> Box<Future<Item = (HashMap<String, Msg>, HashMap<String, Duration>, MsgRoom<String>),
+                       Error = ()>> {
+        let start = Instant::now();
+        let timeout: Duration = timeout.into();
+        let timer = timer.clone();
+        let (asked, others): (Vec<_>, Vec<_>) = players
+            .into_iter()
+            .partition(|client| {
+                           player_ids.contains(&client.id()) || polled_ids.contains(&client.id())
+                       });
+        // Each client gets a room of its own so that its reply can be timed separately.
+        let replies = asked
+            .into_iter()
+            .map(move |client| {
+                let timer = timer.clone();
+                let waited = player_ids.contains(&client.id());
+                let timeout = if waited {
+                    timeout
+                } else {
+                    Duration::from_millis(0)
+                };
+                let mut ids = HashSet::new();
+                ids.insert(client.id());
+                // A `pong` to the lobby's last `ping` may still be on its way, so it is
+                // skipped in favour of the reply that follows. Polled players only have
+                // their forfeits picked out.
+                future::loop_fn(Room::new(vec![client]), move |room| {
+                    let remaining = timeout
+                        .checked_sub(start.elapsed())
+                        .unwrap_or(Duration::from_millis(0));
+                    room.receive(ids.clone())
+                        .with_soft_timeout(remaining, &timer)
+                        .map(move |(msgs, room)| {
+                            let skipped = match msgs.values().next() {
+                                Some(&Msg::Pong) => true,
+                                Some(&Msg::Forfeit) | None => false,
+                                Some(_) => !waited,
+                            };
+                            if skipped {
+                                future::Loop::Continue(room)
+                            } else {
+                                future::Loop::Break((msgs, waited, start.elapsed(), room))
+                            }
+                        })
+                })
+            });
+        let future = future::join_all(replies).map(move |replies| {
+            let mut all_msgs = HashMap::new();
+            let mut response_times = HashMap::new();
+            let mut clients = others;
+            for (msgs, waited, elapsed, room) in replies {
+                for (id, msg) in msgs {
+                    if waited {
+                        response_times.insert(id.clone(), elapsed);
+                    }
+                    all_msgs.insert(id, msg);
+                }
+                clients.extend(room);
+            }
+            (all_msgs, response_times, Room::new(clients))
+        });
+        Box::new(future)
+    }
+
+    /// Gives living players whose connection dropped while the round waited on them until
+    /// their chance to resume runs out, then asks any who came back for their move again, so
+    /// that a blip mid-round doesn't cost them their snake.
+    fn await_dropped_moves
+        (players: MsgRoom<String>,
+         msgs: HashMap<String, Msg>,
+         response_times: HashMap<String, Duration>,
+         round_msg: Msg,
+         living_player_ids: HashSet<String>,
+         timeout: Milliseconds,
+         timer: tokio_timer::Timer,
+         resumptions: Resumptions,
+         releaser: NameReleaser)
+         -> Box<Future<Item = (HashMap<String, Msg>, HashMap<String, Duration>, MsgRoom<String>),
+                       Error = ()>> {
+        let players = Self::reattach(players, &living_player_ids, &resumptions, &releaser);
+        // The resume window of anybody who dropped starts now.
+        let present: HashSet<String> = players.ids().into_iter().collect();
+        let dropped: HashSet<String> = living_player_ids
+            .into_iter()
+            .filter(|id| !present.contains(id))
+            .collect();
+        if dropped.is_empty() {
+            return Box::new(future::ok((msgs, response_times, players)));
+        }
+        for id in &dropped {
+            resumptions.dropped(id, timeout.into());
+        }
+
+        let future = Self::await_reconnections(players,
+                                               dropped.clone(),
+                                               timeout,
+                                               timer.clone(),
+                                               resumptions,
+                                               releaser)
+                .and_then(move |players| {
+                    // Those who resumed missed this round, so they are sent it again.
+                    let (resumed, others): (Vec<_>, Vec<_>) = players
+                        .into_iter()
+                        .partition(|client| dropped.contains(&client.id()));
+                    let resumed_ids: HashSet<String> = resumed.iter().map(Client::id).collect();
+                    Room::new(resumed)
+                        .broadcast_all(round_msg)
+                        .map_err(|_| ())
+                        .and_then(move |resumed| {
+                            let mut players = Room::new(others);
+                            for client in resumed {
+                                players.insert(client);
+                            }
+                            Self::receive_moves(players,
+                                                resumed_ids,
+                                                HashSet::new(),
+                                                timeout,
+                                                &timer)
+                        })
+                })
+                .map(move |(resumed_msgs, resumed_times, players)| {
+                    let mut msgs = msgs;
+                    let mut response_times = response_times;
+                    msgs.extend(resumed_msgs);
+                    response_times.extend(resumed_times);
+                    (msgs, response_times, players)
+                });
+        Box::new(future)
+    }
+
+    /// Removes the snakes of players who forfeited, then plays the round with everybody
+    /// else's moves. Players whose connection closed keep their snake until their chance to
+    /// resume runs out.
+    fn play_turn(game: &mut Game,
+                 msgs: HashMap<String, Msg>,
+                 player_ids: &HashSet<String>)
+                 -> Result<(), ()> {
+        // Once the game has ended, nobody else is left to forfeit.
+        for id in player_ids {
+            if game.state() != &State::Running {
+                return Ok(());
+            }
+            if let Some(&Msg::Forfeit) = msgs.get(id) {
+                handle(game, Event::Forfeit(id.clone()))?;
+            }
+        }
+        if game.state() != &State::Running {
+            return Ok(());
+        }
+        handle(game, Event::Turn(msgs_to_directions(msgs)))
+    }
+
+    /// Brings players waiting in the lobby into the game between rounds, if its rules allow,
+    /// and tells everybody who is now playing.
+    fn admit_arrivals(mut game: Game,
+                      players: MsgRoom<String>,
+                      spectator_tx: mpsc::Sender<Msg>,
+                      arrivals: Arrivals,
+                      resumptions: Resumptions)
+                      -> Box<Future<Item = (Game, MsgRoom<String>, mpsc::Sender<Msg>), Error = ()>> {
+        // Players whose snake died keep their place while they are still connected.
+        let room = MAX_PLAYERS_PER_GAME.saturating_sub(players.ids().len());
+        if !game.game_state().rules.allows_joining_mid_game() || room == 0 {
+            return Box::new(future::ok((game, players, spectator_tx)));
+        }
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let request = LobbyRequest {
+            max_players: room,
+            wait: false,
+            reply_tx: reply_tx,
+        };
+        let Arrivals {
+            lobby_request_tx,
+            roster,
+            releaser,
+            ..
+        } = arrivals;
+        let future = lobby_request_tx
+            .send(request)
+            .map_err(|_| ())
+            .and_then(|_| reply_rx.map_err(|_| ()))
+            .and_then(move |clients| {
+                let mut joined = Vec::new();
+                for client in clients {
+                    let team = roster.team_of(&client.id());
+                    match game.add_player_on_team(client.id(), team) {
+                        Ok(_) => joined.push(client),
+                        Err(e) => {
+                            warn!("Could not add player: {}", e);
+                            let _ = releaser.send(client.id());
+                        }
+                    }
+                }
+                if joined.is_empty() {
+                    return Either::A(future::ok((game, players, spectator_tx)));
+                }
+                resumptions.begin_game(joined.iter().map(Client::id).collect());
+
+                // Newcomers need the game itself before hearing who else is in it.
+                let game_msg = Msg::Game { game: Box::new(game.game_state().clone()) };
+                let players_msg = Msg::players(game.game_state());
+                let future = Room::new(joined)
+                    .broadcast_all(game_msg)
+                    .map_err(|_| ())
+                    .and_then(move |joined| {
+                        let mut players = players;
+                        for client in joined {
+                            players.insert(client);
+                        }
+                        Self::broadcast(players_msg, players, spectator_tx)
+                    })
+                    .map(move |(players, spectator_tx)| (game, players, spectator_tx));
+                Either::B(future)
             });
         Box::new(future)
     }
 
+    /// Between rounds of an endless arena, respawns players whose cooldown is over, lets
+    /// go of players who have left, and sends out the leaderboard when it is due.
+    fn tend_arena(mut game: Game,
+                  players: MsgRoom<String>,
+                  spectator_tx: mpsc::Sender<Msg>,
+                  mut arena: Option<Arena>,
+                  resumptions: &Resumptions,
+                  arrivals: &Arrivals)
+                  -> Box<Future<Item = (Game, MsgRoom<String>, mpsc::Sender<Msg>, Option<Arena>),
+                                Error = ()>> {
+        let mut standings = None;
+        let mut departed = HashSet::new();
+        if let Some(ref mut arena) = arena {
+            if game.state() == &State::Running {
+                let now = Instant::now();
+                arena.record_round(now, game.round_state());
+                // Players without a snake who forfeited or whose connection is gone for
+                // good leave the arena, as the game never ends to let them go.
+                let present: HashSet<String> = players.ids().into_iter().collect();
+                departed = {
+                    let round_state = game.round_state();
+                    game.game_state()
+                        .players
+                        .iter()
+                        .filter(|name| !round_state.snakes.contains_key(*name))
+                        .filter(|name| {
+                                    !present.contains(*name) ||
+                                    round_state.casualties.get(*name) ==
+                                    Some(&CauseOfDeath::Forfeit)
+                                })
+                        .cloned()
+                        .collect()
+                };
+
+                // Everybody else without a snake gets one in time, including players who
+                // joined when there was no room for them.
+                let round_number = game.round_state().round_number;
+                let unspawned: Vec<String> = game.game_state()
+                    .players
+                    .iter()
+                    .filter(|name| {
+                                present.contains(*name) && !departed.contains(*name) &&
+                                !game.round_state().snakes.contains_key(*name)
+                            })
+                    .cloned()
+                    .collect();
+                for name in unspawned {
+                    arena.expect_respawn(name, round_number);
+                }
+                for name in arena.due_respawns(round_number) {
+                    if !present.contains(&name) {
+                        continue;
+                    }
+                    if let Err(e) = game.next(Event::Respawn(name.clone())) {
+                        match *e.kind() {
+                            ErrorKind::NoRoomToSpawn(_) => arena.defer_respawn(name, round_number),
+                            _ => warn!("Could not respawn player: {}", e),
+                        }
+                    }
+                }
+                standings = arena.leaderboard(now, game.round_state());
+            }
+        }
+        for name in &departed {
+            if let Err(e) = game.remove_player(name) {
+                warn!("Could not remove player: {}", e);
+            }
+        }
+
+        // Those still connected, including any who resumed too late, wait for a new game.
+        let mut leaving = resumptions.end_game(&departed);
+        let (left, staying): (Vec<_>, Vec<_>) = players
+            .into_iter()
+            .partition(|client| departed.contains(&client.id()));
+        leaving.extend(left);
+        let players = Room::new(staying);
+
+        let mut future: Box<Future<Item = (MsgRoom<String>, mpsc::Sender<Msg>), Error = ()>> =
+            Box::new(future::ok((players, spectator_tx)));
+        if !leaving.is_empty() {
+            let lobby_tx = arrivals.lobby_tx.clone();
+            future = Box::new(future.and_then(move |(players, spectator_tx)| {
+                let leaving = leaving.into_iter().map(Ok);
+                lobby_tx
+                    .send_all(stream::iter(leaving))
+                    .map_err(|_| ())
+                    .map(move |_| (players, spectator_tx))
+            }));
+        }
+        if !departed.is_empty() {
+            let players_msg = Msg::players(game.game_state());
+            future = Box::new(future.and_then(move |(players, spectator_tx)| {
+                                                  Self::broadcast(players_msg,
+                                                                  players,
+                                                                  spectator_tx)
+                                              }));
+        }
+        if let Some(standings) = standings {
+            let leaderboard_msg = Msg::Leaderboard {
+                standings: standings,
+                game_uuid: game.game_state().uuid,
+            };
+            future = Box::new(future.and_then(move |(players, spectator_tx)| {
+                                                  Self::broadcast(leaderboard_msg,
+                                                                  players,
+                                                                  spectator_tx)
+                                              }));
+        }
+        Box::new(future.map(move |(players, spectator_tx)| (game, players, spectator_tx, arena)))
+    }
+
     fn rounds(game: Game,
               players: MsgRoom<String>,
               spectator_tx: mpsc::Sender<Msg>,
               timeout: Milliseconds,
-              timer: tokio_timer::Timer)
+              timer: tokio_timer::Timer,
+              resumptions: Resumptions,
+              arrivals: Arrivals)
               -> Box<Future<Item = (Game, MsgRoom<String>, mpsc::Sender<Msg>), Error = ()>> {
-        let inputs = (game, players, spectator_tx, timeout, timer.clone());
-        let future = future::loop_fn(inputs, |(a, b, c, d, e)| {
-            Self::round(a, b, c, d, e).map(|ret| {
-                let (game, players, spectator_tx, timeout, timer) = ret;
-                if game.concluded() {
-                    future::Loop::Break((game, players, spectator_tx))
-                } else {
-                    future::Loop::Continue((game, players, spectator_tx, timeout, timer))
-                }
-            })
+        let arena = game.game_state()
+            .rules
+            .arena
+            .map(|rules| Arena::new(rules, Instant::now()));
+        let inputs = (game,
+                      players,
+                      spectator_tx,
+                      timeout,
+                      timer.clone(),
+                      resumptions,
+                      arrivals,
+                      arena);
+        let future = future::loop_fn(inputs, |(a, b, c, d, e, f, g, h)| {
+            let releaser = g.releaser.clone();
+            Self::admit_arrivals(a, b, c, g.clone(), f.clone())
+                .and_then(move |(a, b, c)| Self::round(a, b, c, d, e, f, releaser))
+                .and_then(move |(a, b, c, d, e, f)| {
+                    Self::tend_arena(a, b, c, h, &f, &g).map(move |(game, players, spectator_tx, arena)| {
+                        if game.state() != &State::Running {
+                            future::Loop::Break((game, players, spectator_tx))
+                        } else {
+                            future::Loop::Continue((game,
+                                                    players,
+                                                    spectator_tx,
+                                                    d,
+                                                    e,
+                                                    f,
+                                                    g,
+                                                    arena))
+                        }
+                    })
+                })
         });
         Box::new(future)
     }
@@ -72,26 +507,102 @@ impl GameActor {
              players: MsgRoom<String>,
              spectator_tx: mpsc::Sender<Msg>,
              timeout: Milliseconds,
-             timer: tokio_timer::Timer)
+             timer: tokio_timer::Timer,
+             resumptions: Resumptions,
+             releaser: NameReleaser)
              -> Box<Future<Item = (Game,
                                    MsgRoom<String>,
                                    mpsc::Sender<Msg>,
                                    Milliseconds,
-                                   tokio_timer::Timer),
+                                   tokio_timer::Timer,
+                                   Resumptions),
                            Error = ()>> {
-        let round_msg = Msg::Round {
-            round: Box::new(game.round_state().clone()),
-            game_uuid: game.game_state().uuid,
-        };
-        let future = Self::broadcast(round_msg, players, spectator_tx)
-            .and_then(move |(players, spectator_tx)| {
-                let living_player_ids = game.round_state().snakes.keys().cloned().collect();
-                Self::receive_move_direction(players, living_player_ids, timeout, &timer)
-                    .map(move |(directions, players)| {
-                             game.next(Event::Turn(directions));
-                             (game, players, spectator_tx, timeout, timer)
-                         })
-            });
+        let living_player_ids: HashSet<String> =
+            game.round_state().snakes.keys().cloned().collect();
+        let future = Self::await_reconnections(players,
+                                               living_player_ids.clone(),
+                                               timeout,
+                                               timer.clone(),
+                                               resumptions.clone(),
+                                               releaser.clone())
+                .and_then(move |players| {
+                    // Anybody still missing has lost their connection for good, and nobody
+                    // waits on them again.
+                    let present: HashSet<String> = players.ids().into_iter().collect();
+                    for id in living_player_ids {
+                        if game.state() != &State::Running {
+                            break;
+                        }
+                        if !present.contains(&id) {
+                            handle(&mut game, Event::Disconnect(id))?;
+                        }
+                    }
+                    Ok((game, players))
+                })
+                .and_then(move |(game, players)| {
+                    if game.state() != &State::Running {
+                        return Either::A(future::ok((game,
+                                                     players,
+                                                     spectator_tx,
+                                                     timeout,
+                                                     timer,
+                                                     resumptions)));
+                    }
+                    let round_msg = Msg::Round {
+                        round: Box::new(game.round_state().clone()),
+                        game_uuid: game.game_state().uuid,
+                    };
+                    let living_player_ids: HashSet<String> =
+                        game.round_state().snakes.keys().cloned().collect();
+                    // Dead arena players are polled too, so that they can forfeit while they
+                    // wait to respawn, but the round isn't held up for them.
+                    let dead_player_ids: HashSet<String> =
+                        if game.game_state().rules.arena.is_some() {
+                            game.game_state()
+                                .players
+                                .difference(&living_player_ids)
+                                .cloned()
+                                .collect()
+                        } else {
+                            HashSet::new()
+                        };
+                    let asked_ids: HashSet<String> =
+                        living_player_ids.union(&dead_player_ids).cloned().collect();
+                    let resent_msg = round_msg.clone();
+                    let future = Self::broadcast(round_msg, players, spectator_tx)
+                        .and_then(move |(players, spectator_tx)| {
+                            Self::receive_moves(players,
+                                                living_player_ids.clone(),
+                                                dead_player_ids,
+                                                timeout,
+                                                &timer)
+                                .and_then(move |(msgs, response_times, players)| {
+                                    Self::await_dropped_moves(players,
+                                                              msgs,
+                                                              response_times,
+                                                              resent_msg,
+                                                              living_player_ids,
+                                                              timeout,
+                                                              timer.clone(),
+                                                              resumptions.clone(),
+                                                              releaser)
+                                            .and_then(move |(msgs, response_times, players)| {
+                                                let mut game = game;
+                                                for (id, elapsed) in response_times {
+                                                    game.record_response_time(&id, elapsed);
+                                                }
+                                                Self::play_turn(&mut game, msgs, &asked_ids)?;
+                                                Ok((game,
+                                                    players,
+                                                    spectator_tx,
+                                                    timeout,
+                                                    timer,
+                                                    resumptions))
+                                            })
+                                })
+                        });
+                    Either::B(future)
+                });
         Box::new(future)
     }
 
@@ -99,7 +610,9 @@ impl GameActor {
                players: MsgRoom<String>,
                spectator_tx: mpsc::Sender<Msg>)
                -> Box<Future<Item = (Game, MsgRoom<String>, mpsc::Sender<Msg>), Error = ()>> {
-        let outcome_msg = Msg::outcome(game.round_state().clone(), game.game_state().uuid);
+        let outcome_msg = Msg::outcome(game.game_state(),
+                                       game.round_state().clone(),
+                                       game.stats().clone());
         let future = Self::broadcast(outcome_msg, players, spectator_tx).map(|(players,
                                                                                spectator_tx)| {
                                                                                  (game,
@@ -120,23 +633,51 @@ impl Actor for GameActor {
         let GameActor {
             timer,
             spectator_tx,
+            resumptions,
+            arrivals,
         } = self.clone();
 
-        for id in players.ids() {
-            game.add_player(id);
+        let names = players.ids();
+        for id in names.clone() {
+            let team = arrivals.roster.team_of(&id);
+            if let Err(e) = game.add_player_on_team(id, team) {
+                warn!("Could not add player: {}", e);
+            }
         }
+        if handle(&mut game, Event::Start).is_err() {
+            return Box::new(future::err(()));
+        }
+        resumptions.begin_game(names);
 
         let game_msg = Msg::Game { game: Box::new(game.game_state().clone()) };
+        let rounds_resumptions = resumptions.clone();
         let future = Self::broadcast(game_msg, players, spectator_tx)
             .and_then(move |(players, spectator_tx)| {
-                          Self::rounds(game, players, spectator_tx, timeout, timer)
+                          Self::rounds(game,
+                                       players,
+                                       spectator_tx,
+                                       timeout,
+                                       timer,
+                                       rounds_resumptions,
+                                       arrivals)
                       })
             .and_then(|(game, players, spectator_tx)| Self::outcome(game, players, spectator_tx))
-            .map(|(game, players, _)| (game, players));
+            .map(move |(game, mut players, _)| {
+                     // Players who reconnected too late still belong back in the lobby.
+                     for client in resumptions.end_game(&game.game_state().players) {
+                         players.insert(client);
+                     }
+                     (game, players)
+                 });
         Box::new(future)
     }
 }
 
+// Passes an event to the game, logging any error since the actor's own error type is ().
+fn handle(game: &mut Game, event: Event) -> Result<(), ()> {
+    game.next(event).map_err(|e| error!("Game rejected event: {}", e))
+}
+
 fn msgs_to_directions(msgs: HashMap<String, Msg>) -> HashMap<String, Direction> {
     msgs.into_iter()
         .filter_map(|(id, msg)| if let Msg::Move { direction } = msg {
@@ -146,3 +687,26 @@ fn msgs_to_directions(msgs: HashMap<String, Msg>) -> HashMap<String, Direction>
                     })
         .collect()
 }
+
+#[cfg(all(test, feature = "hexagon"))]
+mod tests {
+    use rand::XorShiftRng;
+
+    use super::*;
+
+    #[test]
+    fn everybody_can_forfeit_in_the_same_round() {
+        let mut game = Game::new(Box::new(XorShiftRng::new_unseeded()), Grid::new(5));
+        let mut msgs = HashMap::new();
+        let mut player_ids = HashSet::new();
+        for name in &["a", "b"] {
+            game.add_player(name.to_string()).unwrap();
+            msgs.insert(name.to_string(), Msg::Forfeit);
+            player_ids.insert(name.to_string());
+        }
+        game.next(Event::Start).unwrap();
+
+        assert!(GameActor::play_turn(&mut game, msgs, &player_ids).is_ok());
+        assert_eq!(game.state(), &State::Ended);
+    }
+}
@@ -1,34 +1,55 @@
 use futures::{future, Future};
 use tokio_timer;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 use state::GridEnum;
-use kabuki::{Actor, ActorRef};
+use kabuki::Actor;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use net::*;
 use utils::*;
+use engine::{Resumptions, TeamRoster};
+use super::nameserver::*;
 
 #[derive(Clone)]
 pub struct Handshake {
     grid: GridEnum,
     timeout: Milliseconds,
+    budget: Milliseconds,
     timer: tokio_timer::Timer,
-    nameserver: ActorRef<String, String, ()>,
+    nameserver: NameserverRef,
+    releaser: NameReleaser,
+    resumptions: Resumptions,
+    roster: TeamRoster,
+    authenticator: Arc<Authenticator>,
 }
 
 impl Handshake {
+    /// Clients get `timeout` for each message and `budget` for the whole handshake, however
+    /// slowly they send.
     pub fn new<G>(grid: G,
                   timeout: Milliseconds,
+                  budget: Milliseconds,
                   timer: tokio_timer::Timer,
-                  nameserver: ActorRef<String, String, ()>)
+                  nameserver: NameserverRef,
+                  releaser: NameReleaser,
+                  resumptions: Resumptions,
+                  roster: TeamRoster,
+                  authenticator: Arc<Authenticator>)
                   -> Handshake
         where G: Into<GridEnum>
     {
         Handshake {
             grid: grid.into(),
             timeout: timeout,
+            budget: budget,
             timer: timer,
             nameserver: nameserver,
+            releaser: releaser,
+            resumptions: resumptions,
+            roster: roster,
+            authenticator: authenticator,
         }
     }
 
@@ -50,22 +71,71 @@ impl Handshake {
                      .map_err(|_| ()))
     }
 
+    /// Gives up on `future` once `deadline` has passed, dropping it along with any client it
+    /// holds so that the connection closes.
+    fn before<F>(future: F,
+                 deadline: Instant,
+                 timer: &tokio_timer::Timer)
+                 -> Box<Future<Item = F::Item, Error = ()>>
+        where F: Future<Error = ()> + 'static,
+              F::Item: 'static
+    {
+        let expiry = timer.sleep(remaining(deadline)).then(|_| Ok(None));
+        Box::new(future
+                     .map(Some)
+                     .select(expiry)
+                     .map_err(|_| ())
+                     .and_then(|(finished, _)| finished.ok_or(())))
+    }
+
     fn rename_and_welcome(unnamed_client: MsgClient<SocketAddr>,
-                          desired_name: String,
+                          request: NameRequest,
                           grid: GridEnum,
                           timeout: Milliseconds,
-                          mut nameserver: ActorRef<String, String, ()>)
+                          deadline: Instant,
+                          timer: tokio_timer::Timer,
+                          mut nameserver: NameserverRef,
+                          releaser: NameReleaser)
                           -> Box<Future<Item = MsgClient<String>, Error = ()>> {
+        let expiry = timer.sleep(remaining(deadline)).then(|_| Ok(None));
         let fut = nameserver
-            .call(desired_name)
-            .and_then(move |final_name| {
-                let client = unnamed_client.rename(final_name);
+            .call(request)
+            .map(Some)
+            .select(expiry)
+            .map_err(|_| ())
+            .and_then(move |(session, pending)| -> Box<Future<Item = MsgClient<String>, Error = ()>> {
+                let session = match session {
+                    Some(session) => session,
+                    None => {
+                        // Out of time: the connection closes now, but any name the
+                        // nameserver hands out afterwards must still be given back.
+                        drop(unnamed_client);
+                        return Box::new(pending.then(move |session| {
+                            if let Ok(Some(Some(session))) = session {
+                                let _ = releaser.send(session.name);
+                            }
+                            Err(())
+                        }));
+                    }
+                };
+                // A missing session means a resume was attempted with a bad token.
+                let session = match session {
+                    Some(session) => session,
+                    None => return Box::new(future::err(())),
+                };
+                let client = unnamed_client.rename(session.name.clone());
                 let welcome_msg = Msg::Welcome {
                     name: client.id(),
                     grid: grid,
                     timeout_millis: Some(timeout),
+                    session: session.token,
                 };
-                Self::transmit(client, welcome_msg)
+                // If the client went away or ran out of time before being welcomed, give its
+                // name back.
+                let welcome = Self::transmit(client, welcome_msg);
+                Box::new(Self::before(welcome, deadline, &timer).map_err(move |_| {
+                    let _ = releaser.send(session.name);
+                }))
             });
         Box::new(fut)
     }
@@ -73,7 +143,8 @@ impl Handshake {
 
 impl Actor for Handshake {
     type Request = MsgClient<SocketAddr>;
-    type Response = (MsgClient<String>, ClientKind);
+    /// `None` if the client resumed a session and was handed straight back to its game.
+    type Response = Option<(MsgClient<String>, ClientKind)>;
     type Error = ();
     type Future = Box<Future<Item = Self::Response, Error = Self::Error>>;
 
@@ -81,21 +152,60 @@ impl Actor for Handshake {
         let Handshake {
             grid,
             timeout,
+            budget,
             timer,
             nameserver,
+            releaser,
+            resumptions,
+            roster,
+            authenticator,
         } = self.clone();
 
-        let version = Self::transmit(unnamed_client, Msg::version());
+        let budget: Duration = budget.into();
+        let deadline = Instant::now() + budget;
+        let version = Self::before(Self::transmit(unnamed_client, Msg::version()),
+                                   deadline,
+                                   &timer);
         let registration_fn = move |unnamed_client| {
-            Self::receive(unnamed_client, timeout, timer).and_then(move |(msg, unnamed_client)| -> Box<Future<Item = (MsgClient<String>, ClientKind), Error = ()>> {
-                if let Msg::Register { desired_name, kind } = msg {
-                    Box::new(Self::rename_and_welcome(unnamed_client, desired_name, grid, timeout, nameserver)
-                        .map(move |client| (client, kind)))
-                } else {
-                    Box::new(future::err(()))
+            let registration = Self::receive(unnamed_client, timeout, timer.clone());
+            Self::before(registration, deadline, &timer).and_then(move |(msg, unnamed_client)| -> Box<Future<Item = Option<(MsgClient<String>, ClientKind)>, Error = ()>> {
+                match msg {
+                    Msg::Register { desired_name, kind, credential, team } => {
+                        let credential = credential.as_ref().map(|c| c.as_str());
+                        let request = match authenticator.authenticate(&desired_name, kind, credential) {
+                            Authentication::Authenticated => NameRequest::Claim(desired_name),
+                            Authentication::Unauthenticated => NameRequest::Register(desired_name),
+                            Authentication::Rejected => return Box::new(future::err(())),
+                        };
+                        Box::new(Self::rename_and_welcome(unnamed_client, request, grid, timeout, deadline, timer, nameserver, releaser)
+                            .map(move |client| {
+                                if kind == ClientKind::Player {
+                                    roster.assign(client.id(), team);
+                                }
+                                Some((client, kind))
+                            }))
+                    }
+                    Msg::Resume { name, session } => {
+                        let request = NameRequest::Resume(name, session);
+                        Box::new(Self::rename_and_welcome(unnamed_client, request, grid, timeout, deadline, timer, nameserver, releaser)
+                            .map(move |client| {
+                                resumptions.park(client).map(|client| (client, ClientKind::Player))
+                            }))
+                    }
+                    _ => Box::new(future::err(())),
                 }
             })
         };
         Box::new(version.and_then(registration_fn))
     }
 }
+
+// How long is left until `deadline`, or nothing if it has passed.
+fn remaining(deadline: Instant) -> Duration {
+    let now = Instant::now();
+    if deadline > now {
+        deadline - now
+    } else {
+        Duration::from_millis(0)
+    }
+}
@@ -1,33 +1,123 @@
-use std::collections::HashSet;
-use futures::future;
-use kabuki::Actor;
+use uuid::Uuid;
+use std::collections::{HashSet, HashMap};
+use futures::{future, Future, Stream};
+use futures::sync::mpsc;
+use kabuki::{Actor, ActorRef};
+
+pub type NameserverRef = ActorRef<NameRequest, Option<Session>, ()>;
+
+/// Names sent here are released once their client has disconnected. Dropping a client
+/// closes its connection but doesn't free its name, so whoever drops one sends it here too.
+pub type NameReleaser = mpsc::UnboundedSender<String>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NameRequest {
+    /// Register a new client, deriving a unique name from the desired one. Reserved names
+    /// are treated as taken.
+    Register(String),
+    /// Register an authenticated owner, who may take their reserved name.
+    Claim(String),
+    /// Reclaim an existing name by presenting the session token it was issued with.
+    Resume(String, Uuid),
+    /// A connection that was given this name has closed. The name can be reused once every
+    /// connection given it has closed, so a late release can't free a resumed name.
+    Release(String),
+}
+
+/// A registered name and the token which allows a reconnecting client to reclaim it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub name: String,
+    pub token: Uuid,
+}
 
 pub struct Nameserver {
-    names: HashSet<String>,
+    // Names currently held, with how many connections were given each one. A resumed client
+    // can hold a name before its old connection's release arrives.
+    names: HashMap<String, usize>,
+    // The most recent session issued for each name. Kept after release so that the owner
+    // can still resume, until somebody else registers the name.
+    sessions: HashMap<String, Uuid>,
+    reserved: HashSet<String>,
 }
 
 impl Default for Nameserver {
     fn default() -> Nameserver {
-        Nameserver { names: HashSet::new() }
+        Nameserver {
+            names: HashMap::new(),
+            sessions: HashMap::new(),
+            reserved: HashSet::new(),
+        }
     }
 }
 
 impl Nameserver {
+    /// Keeps `name` for clients that `Claim` it.
+    pub fn reserve(&mut self, name: String) {
+        self.reserved.insert(name);
+    }
+
+    pub fn register(&mut self, desired_name: String) -> Session {
+        let name = self.uniqueify(desired_name);
+        self.new_session(name)
+    }
+
+    pub fn claim(&mut self, name: String) -> Session {
+        let name = self.find_unused_name(name, false);
+        self.names.insert(name.clone(), 1);
+        self.new_session(name)
+    }
+
+    pub fn resume(&mut self, name: String, token: Uuid) -> Option<Session> {
+        match self.sessions.get(&name) {
+            Some(&issued) if issued == token => {}
+            _ => return None,
+        }
+        *self.names.entry(name.clone()).or_insert(0) += 1;
+        Some(Session {
+                 name: name,
+                 token: token,
+             })
+    }
+
+    pub fn release(&mut self, name: &str) {
+        let held = match self.names.get_mut(name) {
+            Some(count) => {
+                *count -= 1;
+                *count > 0
+            }
+            None => return,
+        };
+        if !held {
+            self.names.remove(name);
+        }
+    }
+
+    fn new_session(&mut self, name: String) -> Session {
+        let token = Uuid::new_v4();
+        self.sessions.insert(name.clone(), token);
+        Session {
+            name: name,
+            token: token,
+        }
+    }
+
     pub fn uniqueify(&mut self, desired_name: String) -> String {
-        let unique_name = self.find_unused_name(desired_name);
-        self.names.insert(unique_name.clone());
+        let unique_name = self.find_unused_name(desired_name, true);
+        self.names.insert(unique_name.clone(), 1);
         unique_name
     }
 
-    fn find_unused_name(&mut self, desired_name: String) -> String {
-        if !self.names.contains(&desired_name) {
+    fn find_unused_name(&mut self, desired_name: String, avoid_reserved: bool) -> String {
+        let reserved = avoid_reserved && self.reserved.contains(&desired_name);
+        if !reserved && !self.names.contains_key(&desired_name) {
             return desired_name;
         }
 
         let mut unique_name = String::new();
         for n in 1.. {
             let potential_name = format!("{}_{}", desired_name, roman_numerals(n));
-            if !self.names.contains(&potential_name) {
+            if !self.names.contains_key(&potential_name) {
                 unique_name = potential_name;
                 break;
             }
@@ -37,16 +127,38 @@ impl Nameserver {
 }
 
 impl Actor for Nameserver {
-    type Request = String;
-    type Response = String;
+    type Request = NameRequest;
+    type Response = Option<Session>;
     type Error = ();
-    type Future = future::FutureResult<String, ()>;
+    type Future = future::FutureResult<Option<Session>, ()>;
 
-    fn call(&mut self, desired_name: Self::Request) -> Self::Future {
-        future::ok(self.uniqueify(desired_name))
+    fn call(&mut self, request: Self::Request) -> Self::Future {
+        future::ok(match request {
+                       NameRequest::Register(desired_name) => Some(self.register(desired_name)),
+                       NameRequest::Claim(name) => Some(self.claim(name)),
+                       NameRequest::Resume(name, token) => self.resume(name, token),
+                       NameRequest::Release(name) => {
+                           self.release(&name);
+                           None
+                       }
+                   })
     }
 }
 
+/// Forwards released names to the nameserver. Lets futures that aren't actors release
+/// names without having to drive the nameserver's response.
+pub fn release_names(nameserver: NameserverRef,
+                     release_rx: mpsc::UnboundedReceiver<String>)
+                     -> Box<Future<Item = (), Error = ()>> {
+    let future = release_rx.for_each(move |name| {
+                                         nameserver
+                                             .clone()
+                                             .call(NameRequest::Release(name))
+                                             .then(|_| Ok(()))
+                                     });
+    Box::new(future)
+}
+
 pub fn roman_numerals(mut value: u64) -> String {
     let mut numerals = "".to_string();
     while value > 0 {
@@ -65,3 +177,24 @@ pub fn roman_numerals(mut value: u64) -> String {
     }
     numerals
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn late_release_keeps_resumed_name() {
+        let mut nameserver = Nameserver::default();
+        let session = nameserver.register("bot".to_string());
+        assert!(nameserver
+                    .resume(session.name.clone(), session.token)
+                    .is_some());
+
+        // The dropped connection is only noticed after the client has resumed.
+        nameserver.release("bot");
+        assert_eq!(nameserver.register("bot".to_string()).name, "bot_I");
+
+        nameserver.release("bot");
+        assert_eq!(nameserver.register("bot".to_string()).name, "bot");
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use state::*;
+
+/// How far back kills count towards the leaderboard.
+const KILL_WINDOW_SECS: u64 = 60 * 60;
+/// How many players each leaderboard lists.
+const LEADERBOARD_SIZE: usize = 10;
+
+/// Keeps an endless arena going: when dead players are due to respawn, and who leads the
+/// rolling leaderboard.
+#[derive(Debug, Clone)]
+pub struct Arena {
+    rules: ArenaRules,
+    // The round each dead player is due to respawn in.
+    respawns: HashMap<String, usize>,
+    kills: VecDeque<(Instant, String)>,
+    last_leaderboard: Instant,
+}
+
+impl Arena {
+    pub fn new(rules: ArenaRules, now: Instant) -> Arena {
+        Arena {
+            rules: rules,
+            respawns: HashMap::new(),
+            kills: VecDeque::new(),
+            last_leaderboard: now,
+        }
+    }
+
+    /// Notes who died in the round just played and who killed them. Players who forfeited
+    /// or disconnected are not brought back.
+    pub fn record_round(&mut self, now: Instant, round_state: &RoundState) {
+        for (name, cause_of_death) in &round_state.casualties {
+            match *cause_of_death {
+                CauseOfDeath::Forfeit |
+                CauseOfDeath::Disconnected => {
+                    self.respawns.remove(name);
+                }
+                _ => {
+                    let due = round_state.round_number + self.rules.respawn_rounds;
+                    self.respawns.insert(name.clone(), due);
+                }
+            }
+        }
+        for killers in round_state.killed_by.values() {
+            for killer in killers {
+                self.kills.push_back((now, killer.clone()));
+            }
+        }
+    }
+
+    /// Takes the players who have waited long enough to respawn by `round_number`.
+    pub fn due_respawns(&mut self, round_number: usize) -> Vec<String> {
+        let due: Vec<String> = self.respawns
+            .iter()
+            .filter(|&(_, &due)| round_number >= due)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &due {
+            self.respawns.remove(name);
+        }
+        due
+    }
+
+    /// Makes sure a player without a snake is due to respawn, by `round_number` at the
+    /// latest if they aren't waiting already.
+    pub fn expect_respawn(&mut self, name: String, round_number: usize) {
+        self.respawns.entry(name).or_insert(round_number);
+    }
+
+    /// Tries respawning a player again after the next round, when there was no room for
+    /// them in this one.
+    pub fn defer_respawn(&mut self, name: String, round_number: usize) {
+        self.respawns.insert(name, round_number + 1);
+    }
+
+    /// The standings, if it is time to send them out again.
+    pub fn leaderboard(&mut self, now: Instant, round_state: &RoundState) -> Option<Standings> {
+        let interval = Duration::from_secs(self.rules.leaderboard_secs);
+        if now.duration_since(self.last_leaderboard) < interval {
+            return None;
+        }
+        self.last_leaderboard = now;
+        Some(self.standings(now, round_state))
+    }
+
+    pub fn standings(&mut self, now: Instant, round_state: &RoundState) -> Standings {
+        let window = Duration::from_secs(KILL_WINDOW_SECS);
+        while self.kills
+                  .front()
+                  .map_or(false, |&(when, _)| now.duration_since(when) > window) {
+            self.kills.pop_front();
+        }
+
+        let longest = round_state
+            .snakes
+            .iter()
+            .map(|(name, snake)| (name.clone(), snake.segments.len()))
+            .collect();
+        let mut tally = HashMap::new();
+        for &(_, ref killer) in &self.kills {
+            *tally.entry(killer.clone()).or_insert(0) += 1;
+        }
+        Standings {
+            longest: top(longest),
+            most_kills: top(tally.into_iter().collect()),
+        }
+    }
+}
+
+// Sorts best first, breaking ties by name so the order is stable, and keeps the leaders.
+fn top(mut scores: Vec<(String, usize)>) -> Vec<(String, usize)> {
+    scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    scores.truncate(LEADERBOARD_SIZE);
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn casualty(round_number: usize, name: &str, killer: &str) -> RoundState {
+        let mut round_state = RoundState::default();
+        round_state.round_number = round_number;
+        round_state
+            .casualties
+            .insert(name.to_string(), CauseOfDeath::CollidedWithSnake);
+        round_state
+            .killed_by
+            .insert(name.to_string(), vec![killer.to_string()]);
+        round_state
+    }
+
+    #[test]
+    fn dead_players_respawn_after_the_cooldown() {
+        let now = Instant::now();
+        let mut arena = Arena::new(ArenaRules::default(), now);
+        arena.record_round(now, &casualty(5, "victim", "killer"));
+        assert!(arena.due_respawns(14).is_empty());
+        assert_eq!(arena.due_respawns(15), vec!["victim"]);
+        assert!(arena.due_respawns(16).is_empty());
+
+        arena.defer_respawn("victim".to_string(), 16);
+        assert_eq!(arena.due_respawns(17), vec!["victim"]);
+
+        arena.record_round(now, &casualty(20, "victim", "killer"));
+        arena.expect_respawn("victim".to_string(), 21);
+        arena.expect_respawn("newcomer".to_string(), 21);
+        assert_eq!(arena.due_respawns(21), vec!["newcomer"]);
+        assert_eq!(arena.due_respawns(30), vec!["victim"]);
+    }
+
+    #[test]
+    fn kills_drop_off_the_leaderboard_after_an_hour() {
+        let start = Instant::now();
+        let mut arena = Arena::new(ArenaRules::default(), start);
+        arena.record_round(start, &casualty(1, "a", "killer"));
+        let later = start + Duration::from_secs(30 * 60);
+        arena.record_round(later, &casualty(2, "b", "killer"));
+        arena.record_round(later, &casualty(2, "c", "other"));
+
+        let round_state = RoundState::default();
+        let standings = arena.standings(later, &round_state);
+        assert_eq!(standings.most_kills,
+                   vec![("killer".to_string(), 2), ("other".to_string(), 1)]);
+        let standings = arena.standings(start + Duration::from_secs(61 * 60), &round_state);
+        assert_eq!(standings.most_kills,
+                   vec![("killer".to_string(), 1), ("other".to_string(), 1)]);
+
+        assert!(arena.leaderboard(later, &round_state).is_some());
+        assert!(arena.leaderboard(later, &round_state).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+use rand::Rng;
+
+use state::*;
+use state::grids::*;
+
+// How many random cells to try before falling back to listing every free cell.
+const RANDOM_ATTEMPTS: usize = 16;
+
+// Whether a cell is in the arena without a wall, snake, food or item on it.
+fn is_free(grid: &Grid, game_state: &GameState, round_state: &RoundState, v: &Vector) -> bool {
+    game_state.map.is_open(grid, v) && grid.is_within_inset(*v, round_state.inset) &&
+    !round_state.occupancy.is_occupied(v) && !round_state.food.contains(v) &&
+    round_state.item_at(v).is_none()
+}
+
+fn free_cells(grid: &Grid, game_state: &GameState, round_state: &RoundState) -> Vec<Vector> {
+    grid.cells()
+        .into_iter()
+        .filter(|v| is_free(grid, game_state, round_state, v))
+        .collect()
+}
+
+// Picks a free cell uniformly. Usually most cells are free, so a few random guesses find one
+// without listing the whole grid.
+fn random_free_cell<R: Rng>(rng: &mut R,
+                            grid: &Grid,
+                            game_state: &GameState,
+                            round_state: &RoundState)
+                            -> Option<Vector> {
+    for _ in 0..RANDOM_ATTEMPTS {
+        let v = grid.random_cell(rng);
+        if is_free(grid, game_state, round_state, &v) {
+            return Some(v);
+        }
+    }
+    rng.choose(&free_cells(grid, game_state, round_state)).cloned()
+}
+
+/// Chooses a cell for a new food according to `game_state.rules.food`. Food is never placed
+/// on top of other food, items, walls or snakes. Returns `None` if there is no room left.
+pub fn place_food<R: Rng>(rng: &mut R,
+                          grid: &Grid,
+                          game_state: &GameState,
+                          round_state: &RoundState)
+                          -> Option<Vector> {
+    match game_state.rules.food.placement {
+        FoodPlacement::Uniform => random_free_cell(rng, grid, game_state, round_state),
+        FoodPlacement::AwayFromHeads => {
+            let free = free_cells(grid, game_state, round_state);
+            let heads: Vec<Vector> = round_state
+                .snakes
+                .values()
+                .filter_map(|snake| snake.segments.first().cloned())
+                .collect();
+            // Each cell is as likely as its distance to the nearest head.
+            let weights: Vec<usize> = free.iter()
+                .map(|v| {
+                         heads
+                             .iter()
+                             .map(|head| grid.distance(v, head))
+                             .min()
+                             .unwrap_or(1)
+                     })
+                .collect();
+            let total: usize = weights.iter().sum();
+            if total == 0 {
+                return None;
+            }
+            let mut target = rng.gen_range(0, total);
+            for (v, weight) in free.into_iter().zip(weights) {
+                if target < weight {
+                    return Some(v);
+                }
+                target -= weight;
+            }
+            None
+        }
+    }
+}
+
+/// Chooses a kind of item by `game_state.rules.items.weights` and a free cell to put it on.
+/// Returns `None` if there is no room left or every weight is zero.
+pub fn place_item<R: Rng>(rng: &mut R,
+                          grid: &Grid,
+                          game_state: &GameState,
+                          round_state: &RoundState)
+                          -> Option<Item> {
+    let weights = &game_state.rules.items.weights;
+    let total: u32 = ItemKind::variants()
+        .iter()
+        .map(|&kind| weights.weight(kind))
+        .sum();
+    if total == 0 {
+        return None;
+    }
+    let mut target = rng.gen_range(0, total);
+    let mut chosen = None;
+    for &kind in ItemKind::variants() {
+        if target < weights.weight(kind) {
+            chosen = Some(kind);
+            break;
+        }
+        target -= weights.weight(kind);
+    }
+
+    match (chosen, random_free_cell(rng, grid, game_state, round_state)) {
+        (Some(kind), Some(position)) => {
+            Some(Item {
+                     kind: kind,
+                     position: position,
+                 })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "hexagon"))]
+mod tests {
+    use rand::XorShiftRng;
+
+    use super::*;
+
+    #[test]
+    fn food_is_only_placed_on_free_cells() {
+        let grid = Grid::new(2);
+        let mut game_state = GameState::new(grid);
+        game_state.rules.food.placement = FoodPlacement::AwayFromHeads;
+        let mut round_state = RoundState::default();
+
+        let mut cells = grid.cells();
+        let free: Vec<Vector> = cells.drain(..2).collect();
+        round_state
+            .snakes
+            .insert("player".to_string(), Snake::new(cells));
+        round_state.reindex();
+
+        let mut rng = XorShiftRng::new_unseeded();
+        for _ in 0..free.len() {
+            let food = place_food(&mut rng, &grid, &game_state, &round_state).unwrap();
+            assert!(free.contains(&food));
+            assert!(round_state.food.insert(food));
+        }
+        assert_eq!(place_food(&mut rng, &grid, &game_state, &round_state), None);
+    }
+}
@@ -0,0 +1,146 @@
+use futures::{Future, Sink, Stream, Poll, Async, AsyncSink};
+use futures::sync::{mpsc, oneshot};
+use comms::Room;
+
+use net::*;
+use actors::NameReleaser;
+
+/// The most players that will be put into a single game.
+pub const MAX_PLAYERS_PER_GAME: usize = 10;
+
+/// A request for up to `max_players` waiting players.
+pub struct LobbyRequest {
+    pub max_players: usize,
+    /// Whether to hold the request until at least one player is waiting, rather than
+    /// answering straight away with nobody.
+    pub wait: bool,
+    pub reply_tx: oneshot::Sender<Vec<MsgClient<String>>>,
+}
+
+/// Holds players between games. Waiting players are pinged periodically and any that stop
+/// responding are disconnected, so that half-open connections never make it into a game.
+pub struct Lobby {
+    player_rx: mpsc::Receiver<MsgClient<String>>,
+    request_rx: mpsc::Receiver<LobbyRequest>,
+    players: MsgRoom<String>,
+    heartbeat: Heartbeat<String>,
+    pending_request: Option<LobbyRequest>,
+    releaser: NameReleaser,
+}
+
+impl Lobby {
+    pub fn new(player_rx: mpsc::Receiver<MsgClient<String>>,
+               request_rx: mpsc::Receiver<LobbyRequest>,
+               heartbeat: Heartbeat<String>,
+               releaser: NameReleaser)
+               -> Lobby {
+        Lobby {
+            player_rx: player_rx,
+            request_rx: request_rx,
+            players: Room::default(),
+            heartbeat: heartbeat,
+            pending_request: None,
+            releaser: releaser,
+        }
+    }
+
+    fn disconnect(&mut self, id: String) {
+        self.heartbeat.forget(&id);
+        self.players.remove(&id);
+        let _ = self.releaser.send(id);
+    }
+
+    fn prune(&mut self) {
+        for id in self.heartbeat.expired() {
+            self.disconnect(id);
+        }
+    }
+
+    fn ping(&mut self) {
+        for id in self.players.ids() {
+            match self.players.start_send((id.clone(), Msg::Ping)) {
+                Ok(AsyncSink::NotReady(_)) |
+                Err(_) => self.disconnect(id),
+                Ok(AsyncSink::Ready) => {}
+            }
+        }
+    }
+
+    fn answer_request(&mut self) {
+        loop {
+            if self.pending_request.is_none() {
+                match self.request_rx.poll() {
+                    Ok(Async::Ready(Some(request))) => self.pending_request = Some(request),
+                    _ => return,
+                }
+            }
+
+            let ids = self.players.ids();
+            let wait = self.pending_request
+                .as_ref()
+                .map_or(false, |request| request.wait);
+            if ids.is_empty() && wait {
+                return;
+            }
+
+            let LobbyRequest {
+                max_players,
+                reply_tx,
+                ..
+            } = self.pending_request.take().unwrap();
+            let players: Vec<_> = ids.into_iter()
+                .take(max_players)
+                .filter_map(|id| {
+                                self.heartbeat.forget(&id);
+                                self.players.remove(&id)
+                            })
+                .collect();
+            // If the requester went away, keep the players waiting for the next one.
+            if let Err(players) = reply_tx.send(players) {
+                for client in players {
+                    self.heartbeat.seen(client.id());
+                    self.players.insert(client);
+                }
+            }
+        }
+    }
+}
+
+impl Future for Lobby {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        loop {
+            match self.player_rx.poll() {
+                Ok(Async::Ready(Some(client))) => {
+                    self.heartbeat.seen(client.id());
+                    self.players.insert(client);
+                }
+                Ok(Async::Ready(None)) => {
+                    // If stream closed, shutdown this future.
+                    self.players.close_all();
+                    return Ok(Async::Ready(()));
+                }
+                Ok(Async::NotReady) | Err(_) => break,
+            }
+        }
+
+        // Any message from a waiting player shows the connection is alive.
+        while let Ok(Async::Ready(Some((id, _)))) = self.players.poll() {
+            self.heartbeat.seen(id);
+        }
+
+        if self.heartbeat.poll_tick() {
+            self.prune();
+            self.ping();
+        }
+
+        let _ = self.players.poll_complete();
+
+        self.prune();
+        self.answer_request();
+
+        Ok(Async::NotReady)
+    }
+}
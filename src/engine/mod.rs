@@ -1,43 +1,100 @@
 use rand::Rng;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::mem;
+use std::sync::Arc;
+use std::time::Duration;
 
+use errors::*;
 use state::*;
 use state::grids::*;
 
 mod spectators;
+mod lobby;
+mod resumptions;
+mod spawns;
+mod food;
+mod roster;
+mod rng;
+mod snapshot;
+mod arena;
 
 pub use self::spectators::*;
+pub use self::lobby::*;
+pub use self::resumptions::*;
+pub use self::spawns::*;
+pub use self::food::*;
+pub use self::roster::*;
+pub use self::rng::*;
+pub use self::snapshot::*;
+pub use self::arena::*;
 
-#[derive(Debug, PartialEq, Clone)]
+/// Where a game is in its lifecycle. Games only move forwards: `Lobby`, `Running`, `Ended`.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum State {
-    Start,
-    Round,
-    End,
-    InvalidTransition(Box<State>, Event),
+    /// Players may join and leave. No rounds have been played.
+    Lobby,
+    Running,
+    /// The game concluded or was aborted, and accepts no further events.
+    Ended,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Event {
+    /// Begins play with the players who have joined.
+    Start,
     Turn(HashMap<String, Direction>),
+    /// A player gives up. Their snake is removed straight away, and in an arena they
+    /// won't respawn.
+    Forfeit(String),
+    /// A player's connection closed. Their snake is removed straight away, and in an arena
+    /// they won't respawn.
+    Disconnect(String),
+    /// Gives a player whose snake died a new one. Fails if there is no room for it.
+    Respawn(String),
+    /// An administrator stops the game where it stands.
+    Abort,
 }
 
+#[derive(Clone)]
 pub struct Game {
     state: State,
-    rng: Box<Rng>,
+    rng: GameRng,
     grid: Grid,
-    game_state: GameState,
+    // Shared between copies of the game, as it only changes when players join or leave.
+    game_state: Arc<GameState>,
     round_state: RoundState,
+    // The round each food dropped by a dead snake appeared in, so it can decay.
+    corpses: HashMap<Vector, usize>,
+    // Whether new food and items are placed. Off when simulating deterministically.
+    spawning: bool,
+    stats: HashMap<String, PlayerStats>,
+    // Snakes removed between rounds, reported among the next round's casualties.
+    retired: HashMap<String, CauseOfDeath>,
 }
 
 impl Game {
     pub fn new(rng: Box<Rng>, grid: Grid) -> Self {
+        Game::with_map(rng, grid, Map::default())
+    }
+
+    pub fn with_map(rng: Box<Rng>, grid: Grid, map: Map) -> Self {
+        Game::with_rules(rng, grid, map, Rules::default())
+    }
+
+    pub fn with_rules(rng: Box<Rng>, grid: Grid, map: Map, rules: Rules) -> Self {
+        let mut game_state = GameState::with_map(grid, map);
+        game_state.rules = rules;
         let mut game = Game {
-            state: State::Start,
-            rng: rng,
+            state: State::Lobby,
+            rng: GameRng::from_rng(rng),
             grid: grid,
-            game_state: GameState::new(grid),
+            game_state: Arc::new(game_state),
             round_state: RoundState::default(),
+            corpses: HashMap::new(),
+            spawning: true,
+            stats: HashMap::new(),
+            retired: HashMap::new(),
         };
 
         // @TODO: Alter API to avoid this juggling.
@@ -48,47 +105,206 @@ impl Game {
         game
     }
 
-    pub fn add_player(&mut self, desired_name: String) -> String {
+    pub fn add_player(&mut self, desired_name: String) -> Result<String> {
+        self.add_player_on_team(desired_name, None)
+    }
+
+    /// Adds a player while the game is in its lobby, or between rounds if the rules let
+    /// players join mid-game. Returns the name they were given.
+    pub fn add_player_on_team(&mut self,
+                              desired_name: String,
+                              team: Option<String>)
+                              -> Result<String> {
+        let joinable = match self.state {
+            State::Lobby => true,
+            State::Running => self.game_state.rules.allows_joining_mid_game(),
+            State::Ended => false,
+        };
+        if !joinable {
+            bail!(ErrorKind::NotInLobby);
+        }
         // Find an unused name based upon the desired_name.
         let mut final_name = desired_name;
         while self.game_state.players.contains(&final_name) {
             final_name += "_";
         }
         // Reserve the new name.
-        self.game_state.players.insert(final_name.clone());
-        // Generate and insert a snake.
-        let head = self.grid.random_cell(&mut self.rng);
-        let snake = Snake::new(vec![head]);
+        {
+            let game_state = Arc::make_mut(&mut self.game_state);
+            game_state.players.insert(final_name.clone());
+            if let Some(team) = team {
+                game_state
+                    .teams
+                    .entry(team)
+                    .or_insert_with(HashSet::new)
+                    .insert(final_name.clone());
+            }
+        }
+        // If the board is full the player joins without a snake.
+        self.stats.insert(final_name.clone(), PlayerStats::default());
+        self.spawn(&final_name);
+
+        Ok(final_name)
+    }
+
+    // Generates and inserts a snake for `name`, returning whether there was room for it.
+    fn spawn(&mut self, name: &str) -> bool {
+        let (snake, facing) =
+            match spawn_snake(&mut self.rng, &self.grid, &self.game_state, &self.round_state) {
+                Some(spawned) => spawned,
+                None => return false,
+            };
+        if let Some(stats) = self.stats.get_mut(name) {
+            stats.max_length = stats.max_length.max(snake.segments.len());
+        }
         self.round_state
-            .snakes
-            .insert(final_name.clone(), snake);
+            .directions
+            .insert(name.to_string(), facing);
+        self.round_state.occupancy.insert_snake(name, &snake);
+        self.round_state.snakes.insert(name.to_string(), snake);
+        true
+    }
 
-        final_name
+    /// Removes a player while the game is in its lobby, or one without a snake from a
+    /// running arena.
+    pub fn remove_player(&mut self, name: &str) -> Result<()> {
+        let removable = match self.state {
+            State::Lobby => true,
+            State::Running => {
+                self.game_state.rules.arena.is_some() &&
+                !self.round_state.snakes.contains_key(name)
+            }
+            State::Ended => false,
+        };
+        if !removable {
+            bail!(ErrorKind::NotInLobby);
+        }
+        if !self.game_state.players.contains(name) {
+            bail!(ErrorKind::UnknownPlayer(name.to_string()));
+        }
+        let game_state = Arc::make_mut(&mut self.game_state);
+        game_state.players.remove(name);
+        for members in game_state.teams.values_mut() {
+            members.remove(name);
+        }
+        game_state
+            .teams
+            .retain(|_, members| !members.is_empty());
+        if let Some(snake) = self.round_state.snakes.remove(name) {
+            self.round_state.occupancy.remove_snake(name, &snake);
+        }
+        self.round_state.directions.remove(name);
+        self.stats.remove(name);
+        Ok(())
     }
 
-    pub fn next(&mut self, event: Event) {
-        self.state = match (self.state.clone(), event) {
-            (State::Start, Event::Turn(directions)) |
-            (State::Round, Event::Turn(directions)) => {
+    pub fn next(&mut self, event: Event) -> Result<()> {
+        self.state = match (self.state, event) {
+            (State::Lobby, Event::Start) => State::Running,
+            (State::Running, Event::Turn(directions)) => {
                 self.advance_round(directions);
-                if self.concluded() {
-                    State::End
-                } else {
-                    State::Round
+                self.running_or_ended()
+            }
+            (State::Running, Event::Forfeit(name)) => {
+                self.retire(&name, CauseOfDeath::Forfeit)?;
+                self.running_or_ended()
+            }
+            (State::Running, Event::Disconnect(name)) => {
+                self.retire(&name, CauseOfDeath::Disconnected)?;
+                self.running_or_ended()
+            }
+            (State::Running, Event::Respawn(name)) => {
+                if !self.game_state.players.contains(&name) {
+                    bail!(ErrorKind::UnknownPlayer(name));
                 }
+                if !self.round_state.snakes.contains_key(&name) && !self.spawn(&name) {
+                    bail!(ErrorKind::NoRoomToSpawn(name));
+                }
+                State::Running
+            }
+            (State::Lobby, Event::Abort) |
+            (State::Running, Event::Abort) => State::Ended,
+            (state, event) => {
+                bail!(ErrorKind::InvalidTransition(format!("{:?}", state), format!("{:?}", event)))
             }
-            (s, e) => State::InvalidTransition(Box::new(s), e),
         };
+        Ok(())
+    }
+
+    fn running_or_ended(&self) -> State {
+        if self.concluded() {
+            State::Ended
+        } else {
+            State::Running
+        }
+    }
+
+    // Removes a player's snake between rounds, dropping food as if it had died. In an arena
+    // a player waiting to respawn can leave too, so that they aren't brought back.
+    fn retire(&mut self, name: &str, cause_of_death: CauseOfDeath) -> Result<()> {
+        if !self.round_state.snakes.contains_key(name) {
+            if self.game_state.rules.arena.is_none() || !self.game_state.players.contains(name) {
+                bail!(ErrorKind::UnknownPlayer(name.to_string()));
+            }
+            self.retired.insert(name.to_string(), cause_of_death);
+            return Ok(());
+        }
+        let mut round_state = mem::replace(&mut self.round_state, RoundState::default());
+        round_state
+            .casualties
+            .insert(name.to_string(), cause_of_death.clone());
+        self.remove_snakes(&mut round_state);
+        self.round_state = round_state;
+        self.retired.insert(name.to_string(), cause_of_death);
+        Ok(())
     }
 
+    /// Whether at most one team, or player on no team, is left alive. Arenas never
+    /// conclude.
     pub fn concluded(&self) -> bool {
-        let number_of_living_snakes = self.round_state.snakes.len();
-        match number_of_living_snakes {
+        if self.game_state.rules.arena.is_some() {
+            return false;
+        }
+        match self.game_state.sides_alive(&self.round_state) {
             0 | 1 => true,
             _ => false,
         }
     }
 
+    pub fn winners(&self) -> HashSet<String> {
+        self.game_state.winners(&self.round_state)
+    }
+
+    pub fn winning_teams(&self) -> HashSet<String> {
+        self.game_state.winning_teams(&self.round_state)
+    }
+
+    /// Plays a round on a copy of the game, leaving this one untouched. Any new food and
+    /// items are placed just as `next` would place them from this game's RNG. To search
+    /// further ahead, clone the game and call `next` on the copies.
+    pub fn simulate(&self, moves: HashMap<String, Direction>) -> RoundState {
+        // Statistics don't affect play, so the copy goes without them.
+        let mut game = Game {
+            state: self.state,
+            rng: self.rng,
+            grid: self.grid,
+            game_state: self.game_state.clone(),
+            round_state: self.round_state.clone(),
+            corpses: self.corpses.clone(),
+            spawning: self.spawning,
+            stats: HashMap::new(),
+            retired: self.retired.clone(),
+        };
+        game.advance_round(moves);
+        game.round_state
+    }
+
+    /// Stops placing new food and items, so that rounds depend only on the moves made.
+    /// Meant for searching ahead on copies of a game rather than for real games.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.spawning = !deterministic;
+    }
+
     pub fn state(&self) -> &State {
         &self.state
     }
@@ -101,13 +317,34 @@ impl Game {
         &self.round_state
     }
 
-    fn advance_round(&mut self, moves: HashMap<String, Direction>) -> RoundState {
-        let mut next_round: RoundState = self.round_state.clone();
+    pub fn stats(&self) -> &HashMap<String, PlayerStats> {
+        &self.stats
+    }
+
+    /// Records how long a player took to reply to the latest round.
+    pub fn record_response_time(&mut self, name: &str, elapsed: Duration) {
+        if let Some(stats) = self.stats.get_mut(name) {
+            stats.record_response(elapsed);
+        }
+    }
+
+    fn advance_round(&mut self, moves: HashMap<String, Direction>) {
+        let mut next_round = mem::replace(&mut self.round_state, RoundState::default());
+        // Bouncing puts snakes back as they were, so they are only copied when they might.
+        let before = match self.game_state.rules.head_on {
+            HeadOnPolicy::Bounce => next_round.snakes.clone(),
+            _ => HashMap::new(),
+        };
 
         // N.B. does not free memory.
         next_round.eaten.clear();
-        next_round.directions.clear();
+        next_round.collected.clear();
         next_round.casualties.clear();
+        next_round.killed_by.clear();
+        next_round.casualties.extend(self.retired.drain());
+
+        // Shrink the arena first so that no food is placed where it is about to vanish.
+        let shrunk = self.shrink_arena(&mut next_round);
 
         // Apply movement and remove snakes that did not move.
         self.snake_movement(&mut next_round, moves);
@@ -116,21 +353,23 @@ impl Game {
         // Grow snakes whose heads collided with a food.
         self.snake_eating(&mut next_round);
         self.manage_food(&mut next_round);
+        self.manage_items(&mut next_round);
 
         // Detect collisions with snakes and remove colliding snakes.
-        self.snake_collisions(&mut next_round);
+        self.snake_collisions(&mut next_round, &before);
         self.remove_snakes(&mut next_round);
 
         // Detect snakes outside grid and remove them.
         // @TODO: I think it is sound to move this to being straight after applying movement,
         // so long as snakes are not removed before collision detection.
-        self.snake_grid_bounds(&mut next_round);
+        self.snake_grid_bounds(&mut next_round, shrunk);
         self.remove_snakes(&mut next_round);
 
+        self.wear_off_effects(&mut next_round);
+        self.update_stats(&next_round);
         next_round.round_number += 1;
 
-        self.round_state = next_round.clone();
-        next_round
+        self.round_state = next_round;
     }
 
     fn snake_movement(&mut self,
@@ -142,11 +381,20 @@ impl Game {
         // Then below if no snake plan is set, we use a default error message.
         // While intricate this very neatly leads to CauseOfDeath.
 
+        let tron = self.game_state.rules.mode == GameMode::Tron;
+        let mut boosted = Vec::new();
+        // The moves of living snakes become this round's directions, saving copies of names.
+        moves.retain(|name, _| next_round.snakes.contains_key(name));
         for (name, snake) in &mut next_round.snakes {
-            match moves.remove(name) {
+            match moves.get(name).cloned() {
                 Some(direction) => {
-                    snake.step_in_direction(direction);
-                    next_round.directions.insert(name.clone(), direction);
+                    step(&self.grid, &mut next_round.occupancy, name, snake, direction, tron);
+                    if snake.boosted > 0 {
+                        boosted.push(name.clone());
+                    }
+                    if let Some(stats) = self.stats.get_mut(name) {
+                        stats.moves_made += 1;
+                    }
                 }
                 _ => {
                     let cause_of_death = CauseOfDeath::NoMoveMade;
@@ -156,40 +404,260 @@ impl Game {
                 }
             }
         }
+        next_round.directions = moves;
+
+        // A boosted snake only takes its second step from an empty cell. Every snake has
+        // taken its first step before any cell is checked, so the order snakes are visited
+        // in doesn't matter.
+        let second_steps: Vec<String> = boosted
+            .into_iter()
+            .filter(|name| {
+                let head = next_round.snakes[name].segments[0];
+                self.game_state.map.is_open(&self.grid, &head) &&
+                next_round.occupancy.owners(&head).len() == 1 &&
+                !next_round.food.contains(&head) &&
+                !next_round.items.iter().any(|item| item.position == head)
+            })
+            .collect();
+        for name in second_steps {
+            let direction = next_round.directions[&name];
+            if let Some(snake) = next_round.snakes.get_mut(&name) {
+                step(&self.grid, &mut next_round.occupancy, &name, snake, direction, tron);
+            }
+        }
     }
 
     fn snake_eating(&mut self, next_round: &mut RoundState) {
+        let growth = self.game_state.rules.food.growth;
+        let item_rules = self.game_state.rules.items.clone();
         for (name, snake) in &mut next_round.snakes {
             if next_round.food.contains(&snake.segments[0]) {
                 // Remove this food only after the full loop, such that N snakes colliding on top of a
                 // food all grow. They immediately die but this way collision with growth of both snakes
                 // is possible.
-                snake.grow();
+                snake.grow(growth);
                 next_round.eaten.insert(name.clone(), snake.segments[0]);
             }
+
+            // Likewise items are removed by `manage_items`.
+            let head = snake.segments[0];
+            let item = match next_round.items.iter().find(|item| item.position == head) {
+                Some(item) => *item,
+                None => continue,
+            };
+            match item.kind {
+                ItemKind::GoldenFood => snake.grow(item_rules.golden_growth),
+                ItemKind::ShrinkPill => {
+                    for segment in snake.shrink(item_rules.shrink_by) {
+                        next_round.occupancy.remove(&segment, name);
+                    }
+                }
+                ItemKind::Invulnerability => snake.invulnerable = item_rules.effect_rounds,
+                ItemKind::SpeedBoost => snake.boosted = item_rules.effect_rounds,
+            }
+            next_round.collected.insert(name.clone(), item);
         }
     }
 
-    fn snake_collisions(&mut self, next_round: &mut RoundState) {
-        for (name, snake) in &next_round.snakes {
-            for coll_snake in next_round.snakes.values() {
-                if snake != coll_snake && snake.has_collided_into(coll_snake) {
-                    next_round
-                        .casualties
-                        .insert(name.clone(), CauseOfDeath::CollidedWithSnake);
-                    break;
+    fn snake_collisions(&mut self,
+                        next_round: &mut RoundState,
+                        before: &HashMap<String, Snake>) {
+        let policy = self.game_state.rules.head_on;
+        let mut bounced = Vec::new();
+        let mut killers = Vec::new();
+        let snakes = &next_round.snakes;
+        let occupancy = &next_round.occupancy;
+        for (name, snake) in snakes {
+            let head = &snake.segments[0];
+            // A snake can have more than one segment on a cell while it grows.
+            let mut owners: Vec<&String> = occupancy
+                .owners(head)
+                .iter()
+                .filter(|&owner| owner != name && !self.passes_through(name, owner))
+                .collect();
+            owners.sort();
+            owners.dedup();
+            let others: Vec<(&String, &Snake)> = owners
+                .into_iter()
+                .filter_map(|owner| snakes.get(owner).map(|other| (owner, other)))
+                .collect();
+            if others.is_empty() || snake.invulnerable > 0 {
+                continue;
+            }
+
+            // Any of another snake's segments here other than its head is part of its body.
+            let body_owners: Vec<&String> = others
+                .iter()
+                .filter(|&&(owner, other)| {
+                            let heads = if other.segments[0] == *head { 1 } else { 0 };
+                            occupancy.count(head, owner) > heads
+                        })
+                .map(|&(owner, _)| owner)
+                .collect();
+            let hit_body = !body_owners.is_empty();
+            let dies = hit_body ||
+                       match policy {
+                           HeadOnPolicy::BothDie => true,
+                           HeadOnPolicy::LongerSurvives => {
+                               others
+                                   .iter()
+                                   .any(|&(_, other)| {
+                                            other.segments.len() >= snake.segments.len()
+                                        })
+                           }
+                           // Snakes bouncing off a wall or the edge die there instead.
+                           HeadOnPolicy::Bounce => {
+                               if self.game_state.map.is_open(&self.grid, head) {
+                                   bounced.push(name.clone());
+                               }
+                               false
+                           }
+                       };
+            if dies {
+                next_round
+                    .casualties
+                    .insert(name.clone(), CauseOfDeath::CollidedWithSnake);
+                let culprits: Vec<String> = if hit_body {
+                    body_owners.iter().map(|&owner| owner.clone()).collect()
+                } else {
+                    others.iter().map(|&(owner, _)| owner.clone()).collect()
+                };
+                killers.extend(culprits.iter().cloned());
+                next_round.killed_by.insert(name.clone(), culprits);
+            }
+        }
+
+        for killer in killers {
+            if let Some(stats) = self.stats.get_mut(&killer) {
+                stats.kills += 1;
+            }
+        }
+        for name in bounced {
+            self.bounce(next_round, before, name);
+        }
+    }
+
+    fn passes_through(&self, a: &str, b: &str) -> bool {
+        self.game_state.rules.teammates_pass_through && self.game_state.are_teammates(a, b)
+    }
+
+    // Puts a snake back as it was before this round's move, keeping anything it ate.
+    fn bounce(&mut self,
+              next_round: &mut RoundState,
+              before: &HashMap<String, Snake>,
+              name: String) {
+        let mut restored = match before.get(&name) {
+            Some(snake) => snake.clone(),
+            None => return,
+        };
+        if next_round.eaten.contains_key(&name) {
+            restored.grow(self.game_state.rules.food.growth);
+        }
+        if let Some(moved) = next_round.snakes.insert(name.clone(), restored.clone()) {
+            next_round.occupancy.remove_snake(&name, &moved);
+        }
+        next_round.occupancy.insert_snake(&name, &restored);
+
+        // The restored tail may now lie under a snake that moved into the space it left.
+        let tail = restored.segments[restored.segments.len() - 1];
+        for owner in next_round.occupancy.owners(&tail) {
+            if *owner != name && !self.passes_through(&name, owner) &&
+               next_round.snakes[owner].segments[0] == tail {
+                next_round
+                    .casualties
+                    .insert(owner.clone(), CauseOfDeath::CollidedWithSnake);
+                next_round
+                    .killed_by
+                    .insert(owner.clone(), vec![name.clone()]);
+                if let Some(stats) = self.stats.get_mut(&name) {
+                    stats.kills += 1;
                 }
             }
         }
     }
 
-    fn snake_grid_bounds(&mut self, next_round: &mut RoundState) {
+    // Applies sudden death for the coming round, returning whether the arena shrank.
+    fn shrink_arena(&mut self, next_round: &mut RoundState) -> bool {
+        let inset = match self.game_state.rules.sudden_death {
+            Some(sudden_death) => sudden_death.inset(next_round.round_number + 1),
+            None => return false,
+        };
+        if inset == next_round.inset {
+            return false;
+        }
+        next_round.inset = inset;
+
+        let grid = self.grid;
+        let outside: Vec<Vector> = next_round
+            .food
+            .iter()
+            .filter(|&food| !grid.is_within_inset(*food, inset))
+            .cloned()
+            .collect();
+        for food in outside {
+            next_round.food.remove(&food);
+            self.corpses.remove(&food);
+        }
+        next_round
+            .items
+            .retain(|item| grid.is_within_inset(item.position, inset));
+        true
+    }
+
+    fn manage_items(&mut self, next_round: &mut RoundState) {
+        for item in next_round.collected.values() {
+            next_round.items.remove(item);
+        }
+
+        let count = match self.game_state.rules.mode {
+            GameMode::Classic if self.spawning => self.game_state.rules.items.count,
+            _ => 0,
+        };
+        while next_round.items.len() < count {
+            match place_item(&mut self.rng, &self.grid, &self.game_state, next_round) {
+                Some(item) => next_round.items.insert(item),
+                None => break,
+            };
+        }
+    }
+
+    fn wear_off_effects(&mut self, next_round: &mut RoundState) {
+        for snake in next_round.snakes.values_mut() {
+            snake.invulnerable = snake.invulnerable.saturating_sub(1);
+            snake.boosted = snake.boosted.saturating_sub(1);
+        }
+    }
+
+    fn update_stats(&mut self, next_round: &RoundState) {
+        for name in next_round.eaten.keys() {
+            if let Some(stats) = self.stats.get_mut(name) {
+                stats.food_eaten += 1;
+            }
+        }
+        for (name, snake) in &next_round.snakes {
+            if let Some(stats) = self.stats.get_mut(name) {
+                stats.rounds_survived += 1;
+                stats.max_length = stats.max_length.max(snake.segments.len());
+            }
+        }
+    }
+
+    fn snake_grid_bounds(&mut self, next_round: &mut RoundState, shrunk: bool) {
+        // Usually only heads need checking, as every other segment was checked when it was
+        // a head. When the arena shrinks, whole bodies may be caught outside.
+        let map = &self.game_state.map;
         for (name, snake) in &next_round.snakes {
-            for &segment in &snake.segments {
-                if !self.grid.is_within_bounds(segment) {
+            let checked = if shrunk { snake.segments.len() } else { 1 };
+            for segment in &snake.segments[..checked] {
+                if !map.is_on_board(&self.grid, segment) ||
+                   !self.grid.is_within_inset(*segment, next_round.inset) {
                     next_round
                         .casualties
                         .insert(name.clone(), CauseOfDeath::CollidedWithBounds);
+                } else if map.is_wall(segment) {
+                    next_round
+                        .casualties
+                        .insert(name.clone(), CauseOfDeath::CollidedWithWall);
                 }
             }
         }
@@ -202,15 +670,22 @@ impl Game {
             // Kill snake if not already killed, and drop food at non-head segments within the grid.
             // @TODO: This code is much cleaner than the last draft but still lots goes on here.
             if let Some(dead_snake) = next_round.snakes.remove(name) {
+                next_round.occupancy.remove_snake(name, &dead_snake);
+                // Light cycles leave no food behind.
+                if self.game_state.rules.mode == GameMode::Tron {
+                    continue;
+                }
                 // Get segments[1..] safely. Directly slicing panics if the Vec had <2 elements.
                 if let Some((_, headless_segments)) = dead_snake.segments.split_first() {
-                    // Only retain segments if within grid.
+                    // Only retain segments if within the arena and not inside a wall.
                     // @TODO: Move this to food management?
-                    let corpse_food: Vec<&Vector> = headless_segments
-                        .iter()
-                        .filter(|&s| self.grid.is_within_bounds(*s))
-                        .collect();
-                    next_round.food.extend(corpse_food);
+                    for segment in headless_segments {
+                        if self.game_state.map.is_open(&self.grid, segment) &&
+                           self.grid.is_within_inset(*segment, next_round.inset) &&
+                           next_round.food.insert(*segment) {
+                            self.corpses.insert(*segment, next_round.round_number);
+                        }
+                    }
                 }
             }
         }
@@ -219,15 +694,57 @@ impl Game {
     fn manage_food(&mut self, next_round: &mut RoundState) {
         for food in next_round.eaten.values() {
             next_round.food.remove(food);
+            self.corpses.remove(food);
+        }
+
+        let rules = self.game_state.rules.food.clone();
+        if let Some(decay) = rules.corpse_decay {
+            let round_number = next_round.round_number;
+            let decayed: Vec<Vector> = self.corpses
+                .iter()
+                .filter(|&(_, &dropped)| round_number >= dropped + decay)
+                .map(|(&food, _)| food)
+                .collect();
+            for food in decayed {
+                self.corpses.remove(&food);
+                next_round.food.remove(&food);
+            }
         }
 
-        if next_round.food.len() < 1 {
-            let new_food = self.grid.random_cell(&mut self.rng);
-            next_round.food.insert(new_food);
+        if !self.spawning || self.game_state.rules.mode == GameMode::Tron {
+            return;
+        }
+        let missing = rules.count.saturating_sub(next_round.food.len());
+        for _ in 0..missing {
+            if self.rng.next_f64() >= rules.spawn_chance {
+                continue;
+            }
+            match place_food(&mut self.rng, &self.grid, &self.game_state, next_round) {
+                Some(food) => next_round.food.insert(food),
+                None => break,
+            };
         }
     }
 }
 
+// Moves a snake one cell, keeping the occupancy index up to date. Light cycles grow as
+// they go.
+fn step(grid: &Grid,
+        occupancy: &mut Occupancy,
+        name: &str,
+        snake: &mut Snake,
+        direction: Direction,
+        tron: bool) {
+    if tron {
+        snake.grow(1);
+    }
+    let head = grid.neighbour(&snake.segments[0], &direction);
+    match snake.step_to(head) {
+        Some(tail) => occupancy.shift(&tail, head, name),
+        None => occupancy.insert(head, name),
+    }
+}
+
 impl fmt::Debug for Game {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Game")
@@ -238,3 +755,375 @@ impl fmt::Debug for Game {
             .finish()
     }
 }
+
+#[cfg(all(test, feature = "hexagon"))]
+mod tests {
+    use rand::XorShiftRng;
+    use std::time::Instant;
+
+    use super::*;
+
+    /// A game in its lobby on a hexagon of `radius`, in which each named player has the
+    /// given snake.
+    pub fn game_with(rules: Rules, radius: usize, snakes: Vec<(&str, Snake)>) -> Game {
+        let mut game = Game::with_rules(Box::new(XorShiftRng::new_unseeded()),
+                                        Grid::new(radius),
+                                        Map::default(),
+                                        rules);
+        place_snakes(&mut game, snakes);
+        game
+    }
+
+    // Adds any of the named players not yet in the game, and swaps in the given snakes for
+    // whatever they spawned with.
+    fn place_snakes(game: &mut Game, snakes: Vec<(&str, Snake)>) {
+        for (name, snake) in snakes {
+            if !game.game_state.players.contains(name) {
+                game.add_player(name.to_string()).unwrap();
+            }
+            game.round_state.snakes.insert(name.to_string(), snake);
+        }
+        game.round_state.reindex();
+    }
+
+    // Rules for games with nothing on the board but the snakes placed there.
+    fn without_food() -> Rules {
+        let mut rules = Rules::default();
+        rules.food.count = 0;
+        rules
+    }
+
+    // A snake of length 3 and one of length 2 move their heads onto the origin.
+    fn head_on(policy: HeadOnPolicy) -> (RoundState, Game) {
+        let mut rules = without_food();
+        rules.head_on = policy;
+        let cell = |x| HexagonVector { x: x, y: 0 };
+        let mut game = game_with(rules,
+                                 5,
+                                 vec![("long", Snake::new(vec![cell(-1), cell(-2), cell(-3)])),
+                                      ("short", Snake::new(vec![cell(1), cell(2)]))]);
+        let before = game.round_state.clone();
+
+        let mut moves = HashMap::new();
+        moves.insert("long".to_string(), HexagonDirection::SouthEast);
+        moves.insert("short".to_string(), HexagonDirection::NorthWest);
+        game.advance_round(moves);
+        (before, game)
+    }
+
+    #[test]
+    fn head_on_both_die() {
+        let (_, game) = head_on(HeadOnPolicy::BothDie);
+        let after = game.round_state();
+        assert!(after.snakes.is_empty());
+        assert_eq!(after.killed_by["long"], vec!["short"]);
+        assert_eq!(after.killed_by["short"], vec!["long"]);
+        assert_eq!(game.stats()["long"].kills, 1);
+        assert_eq!(game.stats()["short"].kills, 1);
+    }
+
+    #[test]
+    fn head_on_longer_survives() {
+        let (_, game) = head_on(HeadOnPolicy::LongerSurvives);
+        let after = game.round_state();
+        assert_eq!(after.snakes.keys().collect::<Vec<_>>(), vec!["long"]);
+        assert_eq!(after.casualties["short"], CauseOfDeath::CollidedWithSnake);
+        assert_eq!(after.killed_by["short"], vec!["long"]);
+        assert_eq!(game.stats()["long"].kills, 1);
+        assert_eq!(game.stats()["short"].kills, 0);
+    }
+
+    #[test]
+    fn head_on_bounce() {
+        let (before, game) = head_on(HeadOnPolicy::Bounce);
+        let after = game.round_state();
+        assert!(after.casualties.is_empty());
+        assert_eq!(after.snakes, before.snakes);
+        assert_eq!(after.occupancy, before.occupancy);
+    }
+
+    #[test]
+    fn sudden_death_kills_bodies_caught_outside() {
+        let mut rules = without_food();
+        rules.sudden_death = Some(SuddenDeath {
+                                      start_round: 0,
+                                      interval: 1,
+                                  });
+        let cell = |x, y| HexagonVector { x: x, y: y };
+        let mut game = game_with(rules,
+                                 3,
+                                 vec![("edge",
+                                       Snake::new(vec![cell(2, 0), cell(3, 0), cell(3, -1)])),
+                                      ("centre", Snake::new(vec![cell(0, -2)]))]);
+
+        let mut moves = HashMap::new();
+        moves.insert("edge".to_string(), HexagonDirection::NorthWest);
+        moves.insert("centre".to_string(), HexagonDirection::South);
+        game.advance_round(moves);
+        let after = game.round_state.clone();
+        assert_eq!(after.inset, 1);
+        assert_eq!(after.casualties["edge"], CauseOfDeath::CollidedWithBounds);
+        assert!(after.snakes.contains_key("centre"));
+    }
+
+    #[test]
+    fn teammates_pass_through_each_other() {
+        let mut rules = without_food();
+        rules.teammates_pass_through = true;
+        let mut game = game_with(rules, 5, vec![]);
+        game.add_player_on_team("a".to_string(), Some("red".to_string())).unwrap();
+        game.add_player_on_team("b".to_string(), Some("red".to_string())).unwrap();
+        let cell = |x| HexagonVector { x: x, y: 0 };
+        place_snakes(&mut game,
+                     vec![("a", Snake::new(vec![cell(-1), cell(-2)])),
+                          ("b", Snake::new(vec![cell(1), cell(2)]))]);
+
+        let mut moves = HashMap::new();
+        moves.insert("a".to_string(), HexagonDirection::SouthEast);
+        moves.insert("b".to_string(), HexagonDirection::NorthWest);
+        game.advance_round(moves);
+        let after = game.round_state.clone();
+        assert!(after.casualties.is_empty());
+        assert!(game.concluded());
+        let winners = game.winners();
+        assert!(winners.contains("a") && winners.contains("b") && !winners.contains("red"));
+        assert_eq!(game.winning_teams().into_iter().collect::<Vec<_>>(), vec!["red"]);
+    }
+
+    #[test]
+    fn items_take_effect_when_collected() {
+        let cell = |x, y| HexagonVector { x: x, y: y };
+        let shrinking = Snake::new(vec![cell(0, 0), cell(-1, 0), cell(-2, 0), cell(-3, 0)]);
+        let mut fast = Snake::new(vec![cell(0, -4)]);
+        fast.boosted = 1;
+        let mut game = game_with(without_food(),
+                                 5,
+                                 vec![("shrinking", shrinking), ("fast", fast)]);
+        game.round_state
+            .items
+            .insert(Item {
+                        kind: ItemKind::ShrinkPill,
+                        position: cell(1, 0),
+                    });
+
+        let mut moves = HashMap::new();
+        moves.insert("shrinking".to_string(), HexagonDirection::SouthEast);
+        moves.insert("fast".to_string(), HexagonDirection::South);
+        game.advance_round(moves);
+        let after = game.round_state.clone();
+        assert_eq!(after.snakes["shrinking"].segments, vec![cell(1, 0), cell(0, 0)]);
+        assert_eq!(after.collected["shrinking"].kind, ItemKind::ShrinkPill);
+        assert!(after.items.is_empty());
+        assert_eq!(after.snakes["fast"].segments, vec![cell(0, -2)]);
+        assert_eq!(after.snakes["fast"].boosted, 0);
+        assert_eq!(after.occupancy, Occupancy::from_snakes(&after.snakes));
+    }
+
+    #[test]
+    fn boosted_snakes_stop_where_another_snake_stepped() {
+        let cell = |x| HexagonVector { x: x, y: 0 };
+        let mut fast = Snake::new(vec![cell(0)]);
+        fast.boosted = 1;
+        let mut game = game_with(without_food(),
+                                 5,
+                                 vec![("fast", fast), ("slow", Snake::new(vec![cell(2)]))]);
+
+        let mut moves = HashMap::new();
+        moves.insert("fast".to_string(), HexagonDirection::SouthEast);
+        moves.insert("slow".to_string(), HexagonDirection::NorthWest);
+        game.advance_round(moves);
+        let after = game.round_state();
+        assert!(after.snakes.is_empty());
+        assert_eq!(after.killed_by["fast"], vec!["slow"]);
+        assert_eq!(after.killed_by["slow"], vec!["fast"]);
+    }
+
+    #[test]
+    fn tron_snakes_grow_every_step_without_food() {
+        let mut rules = Rules::default();
+        rules.mode = GameMode::Tron;
+        let mut game = game_with(rules, 10, vec![]);
+        game.add_player("a".to_string()).unwrap();
+        game.add_player("b".to_string()).unwrap();
+
+        for round in 0..3 {
+            assert!(game.round_state().food.is_empty());
+            let moves = game.round_state().directions.clone();
+            game.advance_round(moves);
+            for snake in game.round_state.snakes.values() {
+                assert_eq!(snake.segments.len(), round + 2);
+            }
+        }
+    }
+
+    #[test]
+    fn simulating_leaves_the_game_untouched() {
+        let mut game = Game::new(Box::new(XorShiftRng::new_unseeded()), Grid::new(5));
+        game.add_player("a".to_string()).unwrap();
+        game.add_player("b".to_string()).unwrap();
+        game.next(Event::Start).unwrap();
+        let before = game.round_state().clone();
+        let moves = before.directions.clone();
+
+        let simulated = game.simulate(moves.clone());
+        assert_eq!(game.round_state(), &before);
+        game.next(Event::Turn(moves.clone())).unwrap();
+        assert_eq!(game.round_state(), &simulated);
+
+        game.set_deterministic(true);
+        let food = game.round_state().food.clone();
+        let moves = game.round_state().directions.clone();
+        let simulated = game.simulate(moves);
+        assert!(simulated.food.is_subset(&food));
+    }
+
+    #[test]
+    fn games_only_move_forwards() {
+        let mut game = Game::new(Box::new(XorShiftRng::new_unseeded()), Grid::new(5));
+        for name in &["a", "b", "c", "d"] {
+            game.add_player(name.to_string()).unwrap();
+        }
+        game.remove_player("d").unwrap();
+        assert!(!game.round_state().snakes.contains_key("d"));
+        assert!(game.next(Event::Turn(HashMap::new())).is_err());
+
+        game.next(Event::Start).unwrap();
+        assert!(game.add_player("d".to_string()).is_err());
+        assert!(game.remove_player("a").is_err());
+
+        game.next(Event::Disconnect("c".to_string())).unwrap();
+        assert_eq!(game.round_state().casualties.get("c"),
+                   Some(&CauseOfDeath::Disconnected));
+        assert_eq!(game.state(), &State::Running);
+        assert!(game.next(Event::Disconnect("c".to_string())).is_err());
+
+        game.next(Event::Forfeit("a".to_string())).unwrap();
+        assert!(!game.round_state().snakes.contains_key("a"));
+        assert_eq!(game.round_state().casualties.get("a"),
+                   Some(&CauseOfDeath::Forfeit));
+        assert_eq!(game.state(), &State::Ended);
+        assert!(game.next(Event::Abort).is_err());
+    }
+
+    #[test]
+    fn forfeits_are_reported_in_the_next_round() {
+        let mut game = Game::new(Box::new(XorShiftRng::new_unseeded()), Grid::new(5));
+        for name in &["a", "b", "c"] {
+            game.add_player(name.to_string()).unwrap();
+        }
+        game.set_deterministic(true);
+        game.next(Event::Start).unwrap();
+        game.next(Event::Forfeit("a".to_string())).unwrap();
+
+        let moves = game.round_state().directions.clone();
+        game.next(Event::Turn(moves)).unwrap();
+        assert_eq!(game.round_state().casualties.get("a"),
+                   Some(&CauseOfDeath::Forfeit));
+        assert!(!game.round_state().snakes.contains_key("a"));
+
+        let moves = game.round_state().directions.clone();
+        game.next(Event::Turn(moves)).unwrap();
+        assert!(!game.round_state().casualties.contains_key("a"));
+    }
+
+    #[test]
+    fn stats_credit_kills_to_the_body_hit() {
+        let cell = |x, y| HexagonVector { x: x, y: y };
+        let body = Snake::new(vec![cell(0, 0), cell(1, 0), cell(2, 0)]);
+        let mut game = game_with(without_food(),
+                                 5,
+                                 vec![("body", body), ("hitter", Snake::new(vec![cell(1, 1)]))]);
+
+        let mut moves = HashMap::new();
+        moves.insert("body".to_string(), HexagonDirection::North);
+        moves.insert("hitter".to_string(), HexagonDirection::North);
+        game.advance_round(moves);
+        assert_eq!(game.round_state.killed_by["hitter"], vec!["body"]);
+        let body = &game.stats()["body"];
+        assert_eq!(body.kills, 1);
+        assert_eq!(body.moves_made, 1);
+        assert_eq!(body.rounds_survived, 1);
+        assert_eq!(body.max_length, 3);
+        let hitter = &game.stats()["hitter"];
+        assert_eq!(hitter.kills, 0);
+        assert_eq!(hitter.rounds_survived, 0);
+    }
+
+    #[test]
+    fn players_join_running_games_away_from_other_heads() {
+        let mut rules = Rules::default();
+        rules.join_mid_game = true;
+        let mut game = game_with(rules, 6, vec![]);
+        game.add_player("a".to_string()).unwrap();
+        game.add_player("b".to_string()).unwrap();
+        game.next(Event::Start).unwrap();
+        let moves = game.round_state().directions.clone();
+        game.next(Event::Turn(moves)).unwrap();
+
+        let name = game.add_player("late".to_string()).unwrap();
+        assert!(game.game_state().players.contains(&name));
+        let head = game.round_state().snakes[&name].segments[0];
+        for (other, snake) in &game.round_state().snakes {
+            if *other != name {
+                assert!(game.grid.distance(&head, &snake.segments[0]) > 2);
+            }
+        }
+    }
+
+    #[test]
+    fn arenas_never_conclude_and_respawn_players() {
+        let mut rules = Rules::default();
+        rules.arena = Some(ArenaRules::default());
+        let mut game = game_with(rules, 5, vec![]);
+        game.add_player("a".to_string()).unwrap();
+        game.add_player("b".to_string()).unwrap();
+        game.next(Event::Start).unwrap();
+        game.next(Event::Turn(HashMap::new())).unwrap();
+        assert!(game.round_state().snakes.is_empty());
+        assert_eq!(game.state(), &State::Running);
+
+        game.next(Event::Respawn("a".to_string())).unwrap();
+        assert!(game.round_state().snakes.contains_key("a"));
+        assert!(game.next(Event::Respawn("nobody".to_string())).is_err());
+    }
+
+    #[test]
+    fn respawning_needs_room_on_the_board() {
+        let mut rules = Rules::default();
+        rules.arena = Some(ArenaRules::default());
+        let mut game = game_with(rules, 2, vec![]);
+        let mut name = game.add_player("player".to_string()).unwrap();
+        while game.round_state().snakes.contains_key(&name) {
+            name = game.add_player("player".to_string()).unwrap();
+        }
+        game.next(Event::Start).unwrap();
+        let error = game.next(Event::Respawn(name.clone())).unwrap_err();
+        match *error.kind() {
+            ErrorKind::NoRoomToSpawn(ref unspawned) => assert_eq!(unspawned, &name),
+            _ => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn arena_players_who_forfeit_while_dead_stay_dead() {
+        let mut rules = Rules::default();
+        rules.arena = Some(ArenaRules::default());
+        let mut game = game_with(rules, 5, vec![]);
+        game.add_player("a".to_string()).unwrap();
+        game.add_player("b".to_string()).unwrap();
+        game.next(Event::Start).unwrap();
+        let now = Instant::now();
+        let mut arena = Arena::new(ArenaRules::default(), now);
+        game.next(Event::Turn(HashMap::new())).unwrap();
+        arena.record_round(now, game.round_state());
+
+        game.next(Event::Forfeit("a".to_string())).unwrap();
+        game.next(Event::Turn(HashMap::new())).unwrap();
+        arena.record_round(now, game.round_state());
+        assert_eq!(arena.due_respawns(game.round_state().round_number + 10),
+                   vec!["b".to_string()]);
+
+        game.remove_player("a").unwrap();
+        assert!(!game.game_state().players.contains("a"));
+    }
+}
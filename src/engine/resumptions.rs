@@ -0,0 +1,76 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+
+use net::*;
+
+/// Shared between the handshake and the games so that a player who reconnects with a valid
+/// session can be handed back to the game they dropped out of.
+#[derive(Clone, Default)]
+pub struct Resumptions {
+    inner: Arc<Mutex<ResumptionsInner>>,
+}
+
+#[derive(Default)]
+struct ResumptionsInner {
+    in_game: HashSet<String>,
+    parked: HashMap<String, MsgClient<String>>,
+    // When each player still missing from their game was first found to have dropped.
+    dropped: HashMap<String, Instant>,
+}
+
+impl Resumptions {
+    pub fn begin_game(&self, names: HashSet<String>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.in_game.extend(names);
+    }
+
+    /// Stops accepting reconnections for these names and returns any that arrived too late
+    /// to be reattached, so they can go back to waiting for a game.
+    pub fn end_game(&self, names: &HashSet<String>) -> Vec<MsgClient<String>> {
+        let mut inner = self.inner.lock().unwrap();
+        for name in names {
+            inner.in_game.remove(name);
+            inner.dropped.remove(name);
+        }
+        names
+            .iter()
+            .filter_map(|name| inner.parked.remove(name))
+            .collect()
+    }
+
+    /// Parks a reconnected client if its name is in a running game. Otherwise the client is
+    /// handed back so it can wait for the next game like any other player.
+    pub fn park(&self, client: MsgClient<String>) -> Option<MsgClient<String>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.in_game.contains(&client.id()) {
+            inner.parked.insert(client.id(), client);
+            None
+        } else {
+            Some(client)
+        }
+    }
+
+    pub fn take(&self, names: &HashSet<String>) -> Vec<MsgClient<String>> {
+        let mut inner = self.inner.lock().unwrap();
+        let clients: Vec<_> = names
+            .iter()
+            .filter_map(|name| inner.parked.remove(name))
+            .collect();
+        for client in &clients {
+            inner.dropped.remove(&client.id());
+        }
+        clients
+    }
+
+    /// Records that a player's connection has dropped, unless it already had, and returns
+    /// when their chance to resume runs out.
+    pub fn dropped(&self, name: &str, timeout: Duration) -> Instant {
+        let mut inner = self.inner.lock().unwrap();
+        let dropped_at = *inner
+                              .dropped
+                              .entry(name.to_string())
+                              .or_insert_with(Instant::now);
+        dropped_at + timeout
+    }
+}
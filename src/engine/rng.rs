@@ -0,0 +1,39 @@
+use rand::Rng;
+
+/// The xorshift generator games draw from. Unlike the generator a game is seeded from, its
+/// state can be saved in a `Snapshot`, so a restored game places food exactly as the
+/// original would have.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GameRng {
+    x: u32,
+    y: u32,
+    z: u32,
+    w: u32,
+}
+
+impl GameRng {
+    pub fn from_rng(mut rng: Box<Rng>) -> GameRng {
+        let mut game_rng = GameRng {
+            x: rng.next_u32(),
+            y: rng.next_u32(),
+            z: rng.next_u32(),
+            w: rng.next_u32(),
+        };
+        // Xorshift never leaves the all-zero state.
+        if game_rng == (GameRng { x: 0, y: 0, z: 0, w: 0 }) {
+            game_rng.w = 1;
+        }
+        game_rng
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        let t = self.x ^ (self.x << 11);
+        self.x = self.y;
+        self.y = self.z;
+        self.z = self.w;
+        self.w = self.w ^ (self.w >> 19) ^ (t ^ (t >> 8));
+        self.w
+    }
+}
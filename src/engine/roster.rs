@@ -0,0 +1,24 @@
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+
+/// Which team each player registered for. Shared between the handshake, which records it,
+/// and the games, which put players on their teams.
+#[derive(Clone, Default)]
+pub struct TeamRoster {
+    teams: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl TeamRoster {
+    /// Records `name`'s team, or that it plays alone.
+    pub fn assign(&self, name: String, team: Option<String>) {
+        let mut teams = self.teams.lock().unwrap();
+        match team {
+            Some(team) => teams.insert(name, team),
+            None => teams.remove(&name),
+        };
+    }
+
+    pub fn team_of(&self, name: &str) -> Option<String> {
+        self.teams.lock().unwrap().get(name).cloned()
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use state::*;
+use super::*;
+
+/// Everything needed to carry on a game from a given round, such as a puzzle position or
+/// the round a bug was reported in. Serialise it to save it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub state: State,
+    pub rng: GameRng,
+    pub grid: Grid,
+    pub game_state: GameState,
+    pub round_state: RoundState,
+    /// Food dropped by dead snakes and the round it was dropped in.
+    #[serde(default)]
+    pub corpses: Vec<(Vector, usize)>,
+    #[serde(default)]
+    pub stats: HashMap<String, PlayerStats>,
+    /// Snakes removed since the last round, to be reported as the next round's casualties.
+    #[serde(default)]
+    pub retired: HashMap<String, CauseOfDeath>,
+}
+
+impl Game {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state.clone(),
+            rng: self.rng,
+            grid: self.grid,
+            game_state: (*self.game_state).clone(),
+            round_state: self.round_state.clone(),
+            corpses: self.corpses.iter().map(|(&food, &dropped)| (food, dropped)).collect(),
+            stats: self.stats.clone(),
+            retired: self.retired.clone(),
+        }
+    }
+
+    pub fn restore(snapshot: Snapshot) -> Game {
+        let mut round_state = snapshot.round_state;
+        round_state.reindex();
+        Game {
+            state: snapshot.state,
+            rng: snapshot.rng,
+            grid: snapshot.grid,
+            game_state: Arc::new(snapshot.game_state),
+            round_state: round_state,
+            corpses: snapshot.corpses.into_iter().collect(),
+            spawning: true,
+            stats: snapshot.stats,
+            retired: snapshot.retired,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "hexagon"))]
+mod tests {
+    use serde_json;
+    use std::collections::HashMap;
+
+    use super::*;
+    use super::super::tests::game_with;
+
+    #[test]
+    fn restored_games_play_on_identically() {
+        let mut rules = Rules::default();
+        rules.food.count = 5;
+        rules.items.count = 2;
+        let mut game = game_with(rules, 8, vec![]);
+        for _ in 0..4 {
+            game.add_player("player".to_string()).unwrap();
+        }
+        game.next(Event::Start).unwrap();
+        let moves: HashMap<String, Direction> = game.round_state().directions.clone();
+        game.next(Event::Turn(moves)).unwrap();
+
+        let json = serde_json::to_string(&game.snapshot()).unwrap();
+        let mut restored = Game::restore(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.round_state(), game.round_state());
+
+        for _ in 0..5 {
+            if game.state() == &State::Ended {
+                break;
+            }
+            let moves: HashMap<String, Direction> = game.round_state().directions.clone();
+            game.next(Event::Turn(moves.clone())).unwrap();
+            restored.next(Event::Turn(moves)).unwrap();
+            assert_eq!(restored.round_state(), game.round_state());
+            assert_eq!(restored.state(), game.state());
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+
+use state::*;
+use state::grids::*;
+
+/// Chooses a body and facing for a new snake according to `game_state.rules.spawns`.
+/// Returns `None` if there is no room left on the board.
+pub fn spawn_snake<R: Rng>(rng: &mut R,
+                           grid: &Grid,
+                           game_state: &GameState,
+                           round_state: &RoundState)
+                           -> Option<(Snake, Direction)> {
+    let rules = &game_state.rules.spawns;
+    let map = &game_state.map;
+    let is_free = |v: &Vector| {
+        map.is_open(grid, v) && grid.is_within_inset(*v, round_state.inset) &&
+        !round_state.occupancy.is_occupied(v) && !round_state.food.contains(v) &&
+        round_state.item_at(v).is_none()
+    };
+
+    let facings: Vec<Direction> = match rules.facing {
+        Facing::Random => Direction::variants().to_vec(),
+        Facing::Fixed(direction) => vec![direction],
+    };
+    // Every free cell with each way a snake could be laid out from it.
+    let candidates: Vec<(Vector, Vec<(Snake, Direction)>)> = grid.cells()
+        .into_iter()
+        .filter(|head| is_free(head))
+        .map(|head| {
+            let layouts: Vec<_> = facings
+                .iter()
+                .filter_map(|&facing| {
+                                lay_out(grid, map, head, facing, rules.initial_length, &is_free)
+                                    .map(|snake| (snake, facing))
+                            })
+                .collect();
+            (head, layouts)
+        })
+        .filter(|&(_, ref layouts)| !layouts.is_empty())
+        .collect();
+
+    // Where there is room, keep clear of cells another snake's head could reach by the time
+    // this one has moved, so that snakes joining a running game aren't hit straight away.
+    let other_heads: Vec<Vector> = round_state
+        .snakes
+        .values()
+        .map(|snake| snake.segments[0])
+        .collect();
+    let is_safe = |head: &Vector| other_heads.iter().all(|other| grid.distance(head, other) > 2);
+    let candidates = if candidates.iter().any(|&(head, _)| is_safe(&head)) {
+        candidates
+            .into_iter()
+            .filter(|&(head, _)| is_safe(&head))
+            .collect()
+    } else {
+        candidates
+    };
+
+    let heads: Vec<&(Vector, Vec<(Snake, Direction)>)> = match rules.strategy {
+        SpawnStrategy::Random => candidates.iter().collect(),
+        SpawnStrategy::Spaced => {
+            let occupied = round_state
+                .snakes
+                .values()
+                .flat_map(|snake| snake.segments.iter().cloned());
+            let distances = distances_from(grid, occupied);
+            let spacing = |head: &Vector| distances.get(head).cloned().unwrap_or(0);
+            let best = candidates.iter().map(|&(head, _)| spacing(&head)).max();
+            candidates
+                .iter()
+                .filter(|&&(head, _)| Some(spacing(&head)) == best)
+                .collect()
+        }
+    };
+
+    let &&(_, ref layouts) = match rng.choose(&heads) {
+        Some(candidate) => candidate,
+        None => return None,
+    };
+    rng.choose(layouts).cloned()
+}
+
+/// How many steps each cell on the board is from the nearest of `sources`, found in a
+/// single breadth-first pass rather than by measuring every cell against every source.
+fn distances_from<I>(grid: &Grid, sources: I) -> HashMap<Vector, usize>
+    where I: IntoIterator<Item = Vector>
+{
+    let mut distances = HashMap::new();
+    let mut queue = VecDeque::new();
+    for v in sources {
+        if distances.insert(v, 0).is_none() {
+            queue.push_back(v);
+        }
+    }
+    while let Some(v) = queue.pop_front() {
+        let next = distances[&v] + 1;
+        for direction in Direction::variants() {
+            let neighbour = grid.neighbour(&v, direction);
+            if grid.is_within_bounds(neighbour) && !distances.contains_key(&neighbour) {
+                distances.insert(neighbour, next);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    distances
+}
+
+/// Lays a snake out in a line behind `head`, so long as every segment is free and the
+/// cell in front of it is on the board.
+fn lay_out<F>(grid: &Grid,
+              map: &Map,
+              head: Vector,
+              facing: Direction,
+              length: usize,
+              is_free: &F)
+              -> Option<Snake>
+    where F: Fn(&Vector) -> bool
+{
+    let ahead = grid.neighbour(&head, &facing);
+    if !map.is_open(grid, &ahead) {
+        return None;
+    }
+    let behind = match Direction::variants()
+              .iter()
+              .find(|direction| grid.neighbour(&ahead, direction) == head) {
+        Some(direction) => *direction,
+        None => return None,
+    };
+
+    let mut segments = vec![head];
+    while segments.len() < length {
+        let next = grid.neighbour(segments.last().unwrap(), &behind);
+        if !is_free(&next) || segments.contains(&next) {
+            return None;
+        }
+        segments.push(next);
+    }
+    Some(Snake::new(segments))
+}
+
+#[cfg(all(test, feature = "hexagon"))]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::super::tests::game_with;
+    use state::*;
+    use state::grids::*;
+
+    #[test]
+    fn spawns_never_overlap_or_cover_food() {
+        let mut rules = Rules::default();
+        rules.spawns.initial_length = 3;
+        let mut game = game_with(rules, 4, vec![]);
+        for _ in 0..8 {
+            game.add_player("player".to_string()).unwrap();
+        }
+
+        let round_state = game.round_state();
+        let mut seen = HashSet::new();
+        for snake in round_state.snakes.values() {
+            assert_eq!(snake.segments.len(), 3);
+            for segment in &snake.segments {
+                assert!(seen.insert(*segment));
+                assert!(!round_state.food.contains(segment));
+            }
+        }
+        assert_eq!(round_state.snakes.len(), 8);
+    }
+
+    #[test]
+    fn distances_match_the_grid() {
+        for grid in &[Grid::new(4), Grid::toroidal(4)] {
+            let sources = vec![HexagonVector { x: 1, y: -2 }, HexagonVector { x: -3, y: 2 }];
+            let distances = super::distances_from(grid, sources.clone());
+            assert_eq!(distances.len(), grid.cells().len());
+            for v in grid.cells() {
+                let nearest = sources
+                    .iter()
+                    .map(|source| grid.distance(&v, source))
+                    .min()
+                    .unwrap();
+                assert_eq!(distances[&v], nearest);
+            }
+        }
+    }
+}
@@ -4,25 +4,38 @@ use futures::sync::mpsc;
 use comms::Room;
 
 use net::*;
+use actors::NameReleaser;
 
 pub struct Spectators {
     spectator_rx: mpsc::Receiver<MsgClient<String>>,
     spectators: MsgRoom<String>,
     msg_rx: mpsc::Receiver<Msg>,
     msg_queue: VecDeque<Msg>,
+    heartbeat: Heartbeat<String>,
+    releaser: NameReleaser,
 }
 
 impl Spectators {
     pub fn new(spectator_rx: mpsc::Receiver<MsgClient<String>>,
-               msg_rx: mpsc::Receiver<Msg>)
+               msg_rx: mpsc::Receiver<Msg>,
+               heartbeat: Heartbeat<String>,
+               releaser: NameReleaser)
                -> Spectators {
         Spectators {
             spectator_rx: spectator_rx,
             spectators: Room::default(),
             msg_rx: msg_rx,
             msg_queue: VecDeque::new(),
+            heartbeat: heartbeat,
+            releaser: releaser,
         }
     }
+
+    fn disconnect(&mut self, id: String) {
+        self.heartbeat.forget(&id);
+        self.spectators.remove(&id);
+        let _ = self.releaser.send(id);
+    }
 }
 
 impl Future for Spectators {
@@ -36,6 +49,7 @@ impl Future for Spectators {
             match self.spectator_rx.poll() {
                 Ok(Async::NotReady) => break,
                 Ok(Async::Ready(Some(client))) => {
+                    self.heartbeat.seen(client.id());
                     self.spectators.insert(client);
                 }
                 Ok(Async::Ready(None)) => {
@@ -62,14 +76,22 @@ impl Future for Spectators {
             }
         }
 
-        // If any spectator sends a message, disconnect them as that behaviour is not
-        // consistent with spectating.
-        match self.spectators.poll() {
-            Ok(Async::Ready(Some((id, _)))) => {
-                // @TODO: Would be nice to have a `close_one` method to avoid the heap Vec.
-                self.spectators.close(vec![id].into_iter().collect());
+        // If any spectator sends a message other than a `pong`, disconnect them as that
+        // behaviour is not consistent with spectating.
+        while let Ok(Async::Ready(Some((id, msg)))) = self.spectators.poll() {
+            if let Msg::Pong = msg {
+                self.heartbeat.seen(id);
+            } else {
+                self.disconnect(id);
+            }
+        }
+
+        // Drop spectators that stopped answering pings, then ping everyone left.
+        if self.heartbeat.poll_tick() {
+            for id in self.heartbeat.expired() {
+                self.disconnect(id);
             }
-            _ => {}
+            self.msg_queue.push_back(Msg::Ping);
         }
 
         match self.spectators.poll_complete() {
@@ -82,9 +104,7 @@ impl Future for Spectators {
             for id in self.spectators.ids() {
                 match self.spectators.start_send((id.clone(), msg.clone())) {
                     Ok(AsyncSink::NotReady(_)) |
-                    Err(_) => {
-                        self.spectators.close(vec![id].into_iter().collect());
-                    }
+                    Err(_) => self.disconnect(id),
                     Ok(AsyncSink::Ready) => {}
                 }
             }
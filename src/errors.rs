@@ -31,6 +31,26 @@ error_chain! {
             description("invalid toolchain name")
             display("invalid toolchain name: '{}'", t)
         }
+        InvalidMap(line: usize, reason: String) {
+            description("invalid map file")
+            display("invalid map file, line {}: {}", line, reason)
+        }
+        InvalidTransition(state: String, event: String) {
+            description("event not allowed in the game's current state")
+            display("cannot handle {} while the game is {}", event, state)
+        }
+        NotInLobby {
+            description("players can only join or leave before the game starts")
+            display("players can only join or leave before the game starts")
+        }
+        UnknownPlayer(name: String) {
+            description("no such player")
+            display("no such player: '{}'", name)
+        }
+        NoRoomToSpawn(name: String) {
+            description("no room on the board for a new snake")
+            display("no room on the board for a snake for '{}'", name)
+        }
     }
 }
 
@@ -30,6 +30,10 @@ extern crate bytes;
 extern crate error_chain;
 extern crate comms;
 extern crate kabuki;
+#[macro_use]
+extern crate log;
+// SHA-2 is used to hash client credentials.
+extern crate sha2;
 
 pub mod state;
 pub mod engine;
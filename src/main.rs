@@ -1,3 +1,4 @@
+#[macro_use]
 extern crate log;
 extern crate env_logger;
 extern crate futures;
@@ -15,12 +16,12 @@ use std::env;
 use std::str;
 use rand::OsRng;
 use std::thread;
+use std::sync::Arc;
 use std::net::SocketAddr;
 use futures::{future, stream, Future, Sink, Stream};
-use futures::future::Either;
-use futures::sync::mpsc;
+use futures::sync::{mpsc, oneshot};
 use tokio_core::net::TcpListener;
-use tokio_core::reactor::Core;
+use tokio_core::reactor::{Core, Handle};
 use tokio_timer::Timer;
 use tokio_io::AsyncRead;
 use comms::{Client, Room};
@@ -52,21 +53,63 @@ fn main() {
     let listener = TcpListener::bind(&addr, &handle).unwrap();
     println!("Listening on {}", addr);
 
-    let grid = Grid::new(25);
+    let grid = grid(25);
     let timeout = Milliseconds::new(5000);
+    // Set `SIRPENT_MAP` to the path of a map file to add walls and holes to the board.
+    let map = match env::var("SIRPENT_MAP") {
+        Ok(path) => Map::from_file(path).unwrap(),
+        Err(_) => Map::default(),
+    };
+    // Set `SIRPENT_RULES` to the path of a JSON rules file to change how games are played.
+    let rules = match env::var("SIRPENT_RULES") {
+        Ok(path) => Rules::from_file(path).unwrap(),
+        Err(_) => Rules::default(),
+    };
+    let ping_interval = Milliseconds::new(2000);
+    let idle_timeout = Milliseconds::new(10000);
+
+    let resumptions = Resumptions::default();
+    let roster = TeamRoster::default();
+    let authenticator = Arc::new(authenticator());
+
+    let mut nameserver = Nameserver::default();
+    for name in authenticator.protected_names() {
+        nameserver.reserve(name);
+    }
+    let nameserver_actor = kabuki::Builder::new().spawn(&handle, nameserver);
+    let (release_tx, release_rx) = mpsc::unbounded();
+    handle.spawn(release_names(nameserver_actor.clone(), release_rx));
 
     let (queue_player_tx, queue_player_rx) = mpsc::channel(3);
+    let (lobby_request_tx, lobby_request_rx) = mpsc::channel(1);
+    let lobby = Lobby::new(queue_player_rx,
+                           lobby_request_rx,
+                           Heartbeat::new(&timer, ping_interval, idle_timeout),
+                           release_tx.clone());
+    handle.spawn(lobby);
 
     let (spectator_tx, spectator_rx) = mpsc::channel(3);
     let (spectator_msg_tx, spectator_msg_rx) = mpsc::channel(3);
-    let spectators = Spectators::new(spectator_rx, spectator_msg_rx);
+    let spectators = Spectators::new(spectator_rx,
+                                     spectator_msg_rx,
+                                     Heartbeat::new(&timer, ping_interval, idle_timeout),
+                                     release_tx.clone());
     handle.spawn(spectators);
 
-    let nameserver = Nameserver::default();
-    let nameserver_actor = kabuki::Builder::new().spawn(&handle, nameserver);
-    let handshaker = Handshake::new(grid.clone(), timeout, timer.clone(), nameserver_actor);
+    let limits = AdmissionLimits::default();
+    let handshaker = Handshake::new(grid.clone(),
+                                    timeout,
+                                    limits.handshake_budget,
+                                    timer.clone(),
+                                    nameserver_actor,
+                                    release_tx.clone(),
+                                    resumptions.clone(),
+                                    roster.clone(),
+                                    authenticator);
     let handshaker_actor = kabuki::Builder::new().spawn(&handle, handshaker);
     handle.spawn(server(listener,
+                        handle.clone(),
+                        Admission::new(limits),
                         handshaker_actor,
                         queue_player_tx.clone(),
                         spectator_tx));
@@ -80,12 +123,21 @@ fn main() {
         let mut lp = Core::new().unwrap();
         let handle = lp.handle();
 
-        let game_actor = GameActor::new(timer.clone(), spectator_msg_tx);
+        let game_actor = GameActor::new(timer.clone(),
+                                        spectator_msg_tx,
+                                        resumptions,
+                                        roster,
+                                        queue_player_tx.clone(),
+                                        lobby_request_tx.clone(),
+                                        release_tx.clone());
         let game_actor = kabuki::Builder::new().spawn(&handle, game_actor);
 
         lp.run(play_games(grid,
+                            map,
+                            rules,
                             queue_player_tx,
-                            Box::new(queue_player_rx.chunks(10)),
+                            lobby_request_tx,
+                            release_tx,
                             game_actor,
                             timeout))
             .unwrap();
@@ -97,9 +149,43 @@ fn main() {
     }
 }
 
+/// A board that wraps around at the edges if `SIRPENT_TOROIDAL` is set.
+#[cfg(feature = "hexagon")]
+fn grid(radius: usize) -> Grid {
+    match env::var("SIRPENT_TOROIDAL") {
+        Ok(_) => Grid::toroidal(radius),
+        Err(_) => Grid::new(radius),
+    }
+}
+
+/// Triangle grids can't wrap around, so they are always bounded.
+#[cfg(not(feature = "hexagon"))]
+fn grid(radius: usize) -> Grid {
+    Grid::new(radius)
+}
+
+/// Reads player and spectator credentials from the file named by `SIRPENT_CREDENTIALS`.
+/// Unauthenticated clients are renamed away from protected names, unless
+/// `SIRPENT_UNAUTHENTICATED_PLAYERS` or `SIRPENT_UNAUTHENTICATED_SPECTATORS` is `reject`.
+fn authenticator() -> Authenticator {
+    let credentials = match env::var("SIRPENT_CREDENTIALS") {
+        Ok(path) => Credentials::from_file(path).unwrap(),
+        Err(_) => Credentials::default(),
+    };
+    let policy = |var| match env::var(var).as_ref().map(|v| v.as_str()) {
+        Ok("reject") => UnauthenticatedPolicy::Reject,
+        _ => UnauthenticatedPolicy::Rename,
+    };
+    Authenticator::new(credentials,
+                       policy("SIRPENT_UNAUTHENTICATED_PLAYERS"),
+                       policy("SIRPENT_UNAUTHENTICATED_SPECTATORS"))
+}
+
 fn server(listener: TcpListener,
+          handle: Handle,
+          admission: Admission,
           handshaker_actor: kabuki::ActorRef<MsgClient<SocketAddr>,
-                                             (MsgClient<String>, ClientKind),
+                                             Option<(MsgClient<String>, ClientKind)>,
                                              ()>,
           player_tx: mpsc::Sender<MsgClient<String>>,
           spectator_tx: mpsc::Sender<MsgClient<String>>)
@@ -108,64 +194,105 @@ fn server(listener: TcpListener,
         .incoming()
         .map_err(|_| ())
         .for_each(move |(socket, addr)| {
-            let msg_transport = socket.framed(MsgCodec);
+            // Refused sockets are closed by dropping them.
+            let permit = match admission.admit(addr.ip()) {
+                Some(permit) => permit,
+                None => return Ok(()),
+            };
+            let msg_transport = AdmittedStream::new(socket, permit).framed(MsgCodec);
             let unnamed_client = Client::new(addr, msg_transport);
 
             let player_tx = player_tx.clone();
             let spectator_tx = spectator_tx.clone();
-            handshaker_actor
+            let handshake = handshaker_actor
                 .clone()
                 .call(unnamed_client)
                 .map_err(|_| ())
-                .map(move |(client, kind)| match kind {
-                         ClientKind::Player => (client, player_tx),
-                         ClientKind::Spectator => (client, spectator_tx),
-                     })
-                .and_then(|(client, tx)| tx.send(client).map_err(|_| ()))
-                .then(|_| Ok(()))
+                .and_then(move |handshaken| {
+                    // Clients who resumed into a running game have already been handed over.
+                    let (client, kind) = match handshaken {
+                        Some(handshaken) => handshaken,
+                        None => return future::Either::A(future::ok(())),
+                    };
+                    let tx = match kind {
+                        ClientKind::Player => player_tx,
+                        ClientKind::Spectator => spectator_tx,
+                    };
+                    future::Either::B(tx.send(client).map(|_| ()).map_err(|_| ()))
+                })
+                .then(|_| Ok(()));
+            // Handshakes run concurrently so that one slow client can't hold up the rest.
+            handle.spawn(handshake);
+            Ok(())
         })
         .then(|_| Ok(()));
     Box::new(server)
 }
 
 fn play_games(grid: Grid,
+              map: Map,
+              rules: Rules,
               add_tx: mpsc::Sender<MsgClient<String>>,
-              add_rx: Box<Stream<Item = Vec<MsgClient<String>>, Error = ()>>,
-              mut game_actor: kabuki::ActorRef<(Game, MsgRoom<String>, Milliseconds),
-                                               (Game, MsgRoom<String>),
-                                               ()>,
+              lobby_request_tx: mpsc::Sender<LobbyRequest>,
+              releaser: NameReleaser,
+              game_actor: kabuki::ActorRef<(Game, MsgRoom<String>, Milliseconds),
+                                           (Game, MsgRoom<String>),
+                                           ()>,
               timeout: Milliseconds)
               -> Box<Future<Item = (), Error = ()>> {
-    let future = add_rx
-        .then(|res| match res {
-                  Ok(players_vec) => {
-                      if players_vec.is_empty() {
-                          Ok(None)
-                      } else {
-                          Ok(Some(players_vec))
-                      }
-                  }
-                  Err(()) => Ok(None),
-              })
-        .for_each(move |res| if let Some(players_vec) = res {
-                      let players = Room::new(players_vec.into_iter().collect());
-                      let game = Game::new(Box::new(OsRng::new().unwrap()), grid.clone());
-                      let add_tx = add_tx.clone();
-                      Either::A(game_actor
-                                    .call((game, players, timeout))
-                                    .and_then(move |(game, players)| {
-                println!("End of game! {:?} {:?}",
-                         game.game_state(),
-                         game.round_state());
-
-                let players_ok = players.into_iter().filter(Client::is_connected).map(Ok);
-                add_tx
-                    .send_all(stream::iter(players_ok))
-                    .map_err(|_| ())
-                    .map(|_| ())
-            }))
-                  } else {
-                      Either::B(future::ok(()))
-                  });
+    let future = future::loop_fn(lobby_request_tx, move |lobby_request_tx| {
+        // Ask the lobby for players. It only answers once somebody live is waiting.
+        let (players_tx, players_rx) = oneshot::channel();
+        let mut game_actor = game_actor.clone();
+        let add_tx = add_tx.clone();
+        let releaser = releaser.clone();
+        let map = map.clone();
+        let rules = rules.clone();
+        lobby_request_tx
+            .send(LobbyRequest {
+                      max_players: MAX_PLAYERS_PER_GAME,
+                      wait: true,
+                      reply_tx: players_tx,
+                  })
+            .map_err(|_| ())
+            .and_then(|lobby_request_tx| {
+                          players_rx
+                              .map_err(|_| ())
+                              .map(move |players_vec| (players_vec, lobby_request_tx))
+                      })
+            .and_then(move |(players_vec, lobby_request_tx)| {
+                let players = Room::new(players_vec.into_iter().collect());
+                let game = Game::with_rules(Box::new(OsRng::new().unwrap()),
+                                            grid.clone(),
+                                            map,
+                                            rules);
+                game_actor
+                    .call((game, players, timeout))
+                    .and_then(move |(game, players)| {
+                        let outcome = Msg::outcome(game.game_state(),
+                                                   game.round_state().clone(),
+                                                   game.stats().clone());
+                        match serde_json::to_string(&outcome) {
+                            Ok(json) => info!(target: "sirpent::outcome", "{}", json),
+                            Err(e) => warn!("Could not serialise outcome: {}", e),
+                        }
+
+                        // Connections that closed as the game ended let go of their names.
+                        let (connected, disconnected): (Vec<_>, Vec<_>) =
+                            players.into_iter().partition(Client::is_connected);
+                        for client in disconnected {
+                            let _ = releaser.send(client.id());
+                        }
+
+                        let players_ok = connected.into_iter().map(Ok);
+                        add_tx
+                            .send_all(stream::iter(players_ok))
+                            .map_err(|_| ())
+                            .map(move |_| -> future::Loop<(), _> {
+                                     future::Loop::Continue(lobby_request_tx)
+                                 })
+                    })
+            })
+    });
     Box::new(future)
 }
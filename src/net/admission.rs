@@ -0,0 +1,170 @@
+use std::io::{self, Read, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
+use futures::Poll;
+use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_core::net::TcpStream;
+
+use utils::*;
+
+/// Limits on incoming connections, so that a flood of idle sockets can't exhaust the server.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdmissionLimits {
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    /// How many handshakes a single IP may start within `handshake_window`.
+    pub max_handshakes_per_ip: usize,
+    pub handshake_window: Milliseconds,
+    /// How long a client has to complete the whole handshake.
+    pub handshake_budget: Milliseconds,
+}
+
+impl Default for AdmissionLimits {
+    fn default() -> AdmissionLimits {
+        AdmissionLimits {
+            max_connections: 256,
+            max_connections_per_ip: 8,
+            max_handshakes_per_ip: 20,
+            handshake_window: Milliseconds::new(60000),
+            handshake_budget: Milliseconds::new(10000),
+        }
+    }
+}
+
+#[derive(Default)]
+struct AdmissionState {
+    connections: usize,
+    connections_per_ip: HashMap<IpAddr, usize>,
+    recent_handshakes: HashMap<IpAddr, VecDeque<Instant>>,
+}
+
+/// Decides whether to accept each new connection. Shared between threads because
+/// connections are closed wherever their client happens to be.
+#[derive(Clone)]
+pub struct Admission {
+    limits: AdmissionLimits,
+    state: Arc<Mutex<AdmissionState>>,
+}
+
+impl Admission {
+    pub fn new(limits: AdmissionLimits) -> Admission {
+        Admission {
+            limits: limits,
+            state: Arc::new(Mutex::new(AdmissionState::default())),
+        }
+    }
+
+    pub fn limits(&self) -> AdmissionLimits {
+        self.limits
+    }
+
+    /// Returns a permit if a connection from `ip` is within limits. The connection counts
+    /// towards the limits until the permit is dropped.
+    pub fn admit(&self, ip: IpAddr) -> Option<Permit> {
+        let mut state = self.state.lock().unwrap();
+
+        let now = Instant::now();
+        let window: Duration = self.limits.handshake_window.into();
+        let recent_handshakes = {
+            let recent = state
+                .recent_handshakes
+                .entry(ip)
+                .or_insert_with(VecDeque::new);
+            while recent
+                      .front()
+                      .map_or(false, |&started| now.duration_since(started) > window) {
+                recent.pop_front();
+            }
+            recent.len()
+        };
+        let connections_from_ip = state.connections_per_ip.get(&ip).cloned().unwrap_or(0);
+
+        if state.connections >= self.limits.max_connections ||
+           connections_from_ip >= self.limits.max_connections_per_ip ||
+           recent_handshakes >= self.limits.max_handshakes_per_ip {
+            return None;
+        }
+
+        state.connections += 1;
+        *state.connections_per_ip.entry(ip).or_insert(0) += 1;
+        state
+            .recent_handshakes
+            .get_mut(&ip)
+            .unwrap()
+            .push_back(now);
+        // Forget IPs whose handshakes have all aged out.
+        state
+            .recent_handshakes
+            .retain(|_, recent| {
+                        recent
+                            .back()
+                            .map_or(false, |&started| now.duration_since(started) <= window)
+                    });
+
+        Some(Permit {
+                 ip: ip,
+                 state: self.state.clone(),
+             })
+    }
+}
+
+pub struct Permit {
+    ip: IpAddr,
+    state: Arc<Mutex<AdmissionState>>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.connections -= 1;
+        let remaining = {
+            let count = state.connections_per_ip.get_mut(&self.ip).unwrap();
+            *count -= 1;
+            *count
+        };
+        if remaining == 0 {
+            state.connections_per_ip.remove(&self.ip);
+        }
+    }
+}
+
+/// A TCP stream which holds its admission `Permit` for as long as it is open.
+pub struct AdmittedStream {
+    inner: TcpStream,
+    _permit: Permit,
+}
+
+impl AdmittedStream {
+    pub fn new(inner: TcpStream, permit: Permit) -> AdmittedStream {
+        AdmittedStream {
+            inner: inner,
+            _permit: permit,
+        }
+    }
+}
+
+impl Read for AdmittedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for AdmittedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl AsyncRead for AdmittedStream {}
+
+impl AsyncWrite for AdmittedStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        AsyncWrite::shutdown(&mut self.inner)
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::path::Path;
+use std::collections::HashMap;
+use serde_json;
+use sha2::{Sha256, Digest};
+
+use super::ClientKind;
+use errors::*;
+
+/// Hashed tokens for names which must authenticate, loaded from a JSON file such as
+/// `{"players": {"46bit": "<sha256 hex>"}, "spectators": {"scoreboard": "<sha256 hex>"}}`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    #[serde(default)]
+    pub players: HashMap<String, String>,
+    #[serde(default)]
+    pub spectators: HashMap<String, String>,
+}
+
+impl Credentials {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Credentials> {
+        let file = File::open(path).chain_err(|| "opening credentials file")?;
+        serde_json::from_reader(file).chain_err(|| "parsing credentials file")
+    }
+
+    pub fn hash_token(token: &str) -> String {
+        let mut hasher = Sha256::default();
+        hasher.input(token.as_bytes());
+        hasher
+            .result()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    fn table(&self, kind: ClientKind) -> &HashMap<String, String> {
+        match kind {
+            ClientKind::Player => &self.players,
+            ClientKind::Spectator => &self.spectators,
+        }
+    }
+}
+
+/// What to do with clients who register without a valid credential.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnauthenticatedPolicy {
+    /// Let them in, but never under a name reserved for an authenticated owner.
+    Rename,
+    /// Close their connection.
+    Reject,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Authentication {
+    Authenticated,
+    Unauthenticated,
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct Authenticator {
+    credentials: Credentials,
+    player_policy: UnauthenticatedPolicy,
+    spectator_policy: UnauthenticatedPolicy,
+}
+
+impl Default for Authenticator {
+    fn default() -> Authenticator {
+        Authenticator::new(Credentials::default(),
+                           UnauthenticatedPolicy::Rename,
+                           UnauthenticatedPolicy::Rename)
+    }
+}
+
+impl Authenticator {
+    pub fn new(credentials: Credentials,
+               player_policy: UnauthenticatedPolicy,
+               spectator_policy: UnauthenticatedPolicy)
+               -> Authenticator {
+        Authenticator {
+            credentials: credentials,
+            player_policy: player_policy,
+            spectator_policy: spectator_policy,
+        }
+    }
+
+    /// Every name with a credential, which should be reserved in the `Nameserver`.
+    pub fn protected_names(&self) -> Vec<String> {
+        self.credentials
+            .players
+            .keys()
+            .chain(self.credentials.spectators.keys())
+            .cloned()
+            .collect()
+    }
+
+    pub fn authenticate(&self,
+                        name: &str,
+                        kind: ClientKind,
+                        credential: Option<&str>)
+                        -> Authentication {
+        let status = match (self.credentials.table(kind).get(name), credential) {
+            (Some(expected), Some(token)) => {
+                if constant_time_eq(expected.as_bytes(),
+                                    Credentials::hash_token(token).as_bytes()) {
+                    Authentication::Authenticated
+                } else {
+                    // A wrong credential is never let in under another name.
+                    return Authentication::Rejected;
+                }
+            }
+            _ => Authentication::Unauthenticated,
+        };
+
+        let policy = match kind {
+            ClientKind::Player => self.player_policy,
+            ClientKind::Spectator => self.spectator_policy,
+        };
+        match (status, policy) {
+            (Authentication::Unauthenticated, UnauthenticatedPolicy::Reject) => {
+                Authentication::Rejected
+            }
+            (status, _) => status,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,58 @@
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+use futures::{Async, Stream};
+use tokio_timer;
+
+use utils::*;
+
+/// Tracks when each client was last heard from so that half-open connections can be
+/// found and dropped. `poll_tick` says when it is time to send another `Msg::Ping`.
+pub struct Heartbeat<I> {
+    interval: tokio_timer::Interval,
+    idle_timeout: Duration,
+    last_seen: HashMap<I, Instant>,
+}
+
+impl<I> Heartbeat<I>
+    where I: Eq + Hash + Clone
+{
+    pub fn new(timer: &tokio_timer::Timer,
+               ping_interval: Milliseconds,
+               idle_timeout: Milliseconds)
+               -> Heartbeat<I> {
+        Heartbeat {
+            interval: timer.interval(ping_interval.into()),
+            idle_timeout: idle_timeout.into(),
+            last_seen: HashMap::new(),
+        }
+    }
+
+    pub fn seen(&mut self, id: I) {
+        self.last_seen.insert(id, Instant::now());
+    }
+
+    pub fn forget(&mut self, id: &I) {
+        self.last_seen.remove(id);
+    }
+
+    /// Returns true if at least one ping interval has elapsed since the last call.
+    /// Polls the interval until it is not ready so that the current task gets woken.
+    pub fn poll_tick(&mut self) -> bool {
+        let mut ticked = false;
+        while let Ok(Async::Ready(Some(()))) = self.interval.poll() {
+            ticked = true;
+        }
+        ticked
+    }
+
+    /// Clients that have not been heard from within the idle timeout.
+    pub fn expired(&self) -> HashSet<I> {
+        let now = Instant::now();
+        self.last_seen
+            .iter()
+            .filter(|&(_, &seen)| now.duration_since(seen) > self.idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
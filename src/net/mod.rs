@@ -1,13 +1,18 @@
 mod msg;
+mod heartbeat;
+mod credentials;
+mod admission;
 
 pub use self::msg::*;
+pub use self::heartbeat::*;
+pub use self::credentials::*;
+pub use self::admission::*;
 
 use std::io;
 use std::str;
 use serde_json;
 use bytes::{BufMut, BytesMut};
 use tokio_io::codec::{Encoder, Decoder, Framed};
-use tokio_core::net::TcpStream;
 
 use comms::{Client, Room};
 use utils::*;
@@ -22,7 +27,7 @@ pub enum ClientKind {
     Spectator,
 }
 
-pub type MsgTransport = Framed<TcpStream, MsgCodec>;
+pub type MsgTransport = Framed<AdmittedStream, MsgCodec>;
 
 // https://github.com/tokio-rs/tokio-line/blob/master/src/framed_transport.rs
 #[derive(Clone, Copy, Debug)]
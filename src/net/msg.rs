@@ -1,5 +1,5 @@
 use uuid::Uuid;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use super::*;
 use utils::*;
@@ -15,23 +15,48 @@ pub enum Msg {
     Register {
         desired_name: String,
         kind: ClientKind,
+        #[serde(default)]
+        credential: Option<String>,
+        #[serde(default)]
+        team: Option<String>,
     },
+    Resume { name: String, session: Uuid },
     Welcome {
         name: String,
         grid: GridEnum,
         timeout_millis: Option<Milliseconds>,
+        session: Uuid,
     },
     Game { game: Box<GameState> },
+    /// Everybody in a running game, sent when players join it.
+    Players {
+        players: HashSet<String>,
+        teams: HashMap<String, HashSet<String>>,
+        game_uuid: Uuid,
+    },
     Round {
         round: Box<RoundState>,
         game_uuid: Uuid,
     },
     Move { direction: Direction },
+    /// Gives up the current game. The player's snake is removed at once.
+    Forfeit,
+    /// Sent periodically during an endless arena.
+    Leaderboard {
+        standings: Standings,
+        game_uuid: Uuid,
+    },
     Outcome {
         winners: HashSet<String>,
+        #[serde(default)]
+        winning_teams: HashSet<String>,
         conclusion: Box<RoundState>,
         game_uuid: Uuid,
+        #[serde(default)]
+        stats: HashMap<String, PlayerStats>,
     },
+    Ping,
+    Pong,
 }
 
 impl Msg {
@@ -42,21 +67,39 @@ impl Msg {
         }
     }
 
-    pub fn welcome<G>(name: String, grid: G, timeout_millis: Option<Milliseconds>) -> Msg
+    pub fn welcome<G>(name: String,
+                      grid: G,
+                      timeout_millis: Option<Milliseconds>,
+                      session: Uuid)
+                      -> Msg
         where G: Into<GridEnum>
     {
         Msg::Welcome {
             name: name,
             grid: grid.into(),
             timeout_millis: timeout_millis,
+            session: session,
+        }
+    }
+
+    pub fn players(game_state: &GameState) -> Msg {
+        Msg::Players {
+            players: game_state.players.clone(),
+            teams: game_state.teams.clone(),
+            game_uuid: game_state.uuid,
         }
     }
 
-    pub fn outcome(final_round_state: RoundState, game_uuid: Uuid) -> Msg {
+    pub fn outcome(game_state: &GameState,
+                   final_round_state: RoundState,
+                   stats: HashMap<String, PlayerStats>)
+                   -> Msg {
         Msg::Outcome {
-            winners: final_round_state.snakes.keys().cloned().collect(),
+            winners: game_state.winners(&final_round_state),
+            winning_teams: game_state.winning_teams(&final_round_state),
             conclusion: Box::new(final_round_state),
-            game_uuid: game_uuid,
+            game_uuid: game_state.uuid,
+            stats: stats,
         }
     }
 }
@@ -8,15 +8,72 @@ pub struct GameState {
     pub uuid: Uuid,
     pub grid: GridEnum,
     pub players: HashSet<String>,
+    #[serde(default)]
+    pub map: Map,
+    #[serde(default)]
+    pub rules: Rules,
+    /// The members of each team. Players on no team play for themselves.
+    #[serde(default)]
+    pub teams: HashMap<String, HashSet<String>>,
 }
 
 impl GameState {
     pub fn new(grid: Grid) -> GameState {
+        GameState::with_map(grid, Map::default())
+    }
+
+    pub fn with_map(grid: Grid, map: Map) -> GameState {
         GameState {
             uuid: Uuid::new_v4(),
             grid: grid.into(),
             players: HashSet::new(),
+            map: map,
+            rules: Rules::default(),
+            teams: HashMap::new(),
+        }
+    }
+
+    pub fn team_of(&self, player: &str) -> Option<&String> {
+        self.teams
+            .iter()
+            .find(|&(_, members)| members.contains(player))
+            .map(|(team, _)| team)
+    }
+
+    pub fn are_teammates(&self, a: &str, b: &str) -> bool {
+        match self.team_of(a) {
+            Some(team) => self.teams[team].contains(b),
+            None => false,
+        }
+    }
+
+    /// How many teams, or players on no team, still have a snake alive.
+    pub fn sides_alive(&self, round_state: &RoundState) -> usize {
+        let mut teams = HashSet::new();
+        let mut loners = 0;
+        for name in round_state.snakes.keys() {
+            match self.team_of(name) {
+                Some(team) => {
+                    teams.insert(team);
+                }
+                None => loners += 1,
+            }
         }
+        teams.len() + loners
+    }
+
+    /// The surviving players.
+    pub fn winners(&self, round_state: &RoundState) -> HashSet<String> {
+        round_state.snakes.keys().cloned().collect()
+    }
+
+    /// The teams the surviving players play for.
+    pub fn winning_teams(&self, round_state: &RoundState) -> HashSet<String> {
+        round_state
+            .snakes
+            .keys()
+            .filter_map(|name| self.team_of(name).cloned())
+            .collect()
     }
 }
 
@@ -24,8 +81,34 @@ impl GameState {
 pub struct RoundState {
     pub round_number: usize,
     pub food: HashSet<Vector>,
+    #[serde(default)]
+    pub items: HashSet<Item>,
     pub eaten: HashMap<String, Vector>,
+    /// Items picked up this round.
+    #[serde(default)]
+    pub collected: HashMap<String, Item>,
     pub snakes: HashMap<String, Snake>,
     pub directions: HashMap<String, Direction>,
     pub casualties: HashMap<String, CauseOfDeath>,
+    /// The snakes each casualty of a collision ran into: the owner of the body it hit, or
+    /// every snake in a head-on collision.
+    #[serde(default)]
+    pub killed_by: HashMap<String, Vec<String>>,
+    /// How many rings sudden death has removed from the edge of the grid.
+    #[serde(default)]
+    pub inset: usize,
+    /// Kept up to date by the engine. Not sent to clients, so call `reindex` after
+    /// deserialising.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub occupancy: Occupancy,
+}
+
+impl RoundState {
+    pub fn item_at(&self, v: &Vector) -> Option<&Item> {
+        self.items.iter().find(|item| item.position == *v)
+    }
+
+    pub fn reindex(&mut self) {
+        self.occupancy = Occupancy::from_snakes(&self.snakes);
+    }
 }
@@ -1,5 +1,5 @@
 use rand::Rng;
-use std::cmp::max;
+use std::cmp::{max, min};
 
 use super::traits::*;
 
@@ -98,12 +98,67 @@ impl VectorTrait for HexagonVector {
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
 pub struct HexagonGrid {
     pub radius: usize,
+    #[serde(default)]
+    pub topology: Topology,
 }
 
 impl HexagonGrid {
     #[allow(dead_code)]
     pub fn new(radius: usize) -> HexagonGrid {
-        HexagonGrid { radius: radius }
+        HexagonGrid {
+            radius: radius,
+            topology: Topology::Bounded,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn toroidal(radius: usize) -> HexagonGrid {
+        HexagonGrid {
+            radius: radius,
+            topology: Topology::Toroidal,
+        }
+    }
+
+    // The cell at `index` in the order `cells` lists them, found column by column
+    // rather than by building the whole list.
+    fn nth_cell(&self, index: usize) -> HexagonVector {
+        let r = self.radius as isize;
+        let mut index = index as isize;
+        for x in -r..r + 1 {
+            let column = 2 * r + 1 - x.abs();
+            if index < column {
+                return HexagonVector {
+                           x: x,
+                           y: max(-r, -x - r) + index,
+                       };
+            }
+            index -= column;
+        }
+        panic!("cell index out of range");
+    }
+
+    // The centres of the six copies of the board which tile the plane around it.
+    // http://www.redblobgames.com/grids/hexagons/#wraparound
+    fn mirror_centres(&self) -> [HexagonVector; 6] {
+        let r = self.radius as isize;
+        [HexagonVector {
+             x: 2 * r + 1,
+             y: -r,
+         },
+         HexagonVector {
+             x: r + 1,
+             y: -2 * r - 1,
+         },
+         HexagonVector { x: -r, y: -r - 1 },
+         HexagonVector {
+             x: -2 * r - 1,
+             y: r,
+         },
+         HexagonVector {
+             x: -r - 1,
+             y: 2 * r + 1,
+         },
+         HexagonVector { x: r, y: r + 1 }]
     }
 }
 
@@ -119,32 +174,69 @@ impl GridTrait for HexagonGrid {
         HexagonVector { x: 0, y: 0 }.distance(&v) <= self.radius
     }
 
+    fn is_within_inset(&self, v: HexagonVector, inset: usize) -> bool {
+        inset <= self.radius && HexagonVector { x: 0, y: 0 }.distance(&v) <= self.radius - inset
+    }
+
     fn cells(&self) -> Vec<HexagonVector> {
-        unimplemented!();
+        let r = self.radius as isize;
+        let mut cells = Vec::new();
+        for x in -r..r + 1 {
+            for y in max(-r, -x - r)..min(r, -x + r) + 1 {
+                cells.push(HexagonVector { x: x, y: y });
+            }
+        }
+        cells
     }
 
     fn random_cell<R: Rng>(&self, rng: &mut R) -> HexagonVector {
-        let isize_radius = self.radius as isize;
-
-        let mut x;
-        let mut y;
-        let mut z;
-        // @TODO: Determine a nicer, unbiased way to select these parameters.
-        loop {
-            x = rng.gen_range(-isize_radius, isize_radius + 1);
-            y = rng.gen_range(-isize_radius, isize_radius + 1);
-            z = x + y;
-            if (-isize_radius <= z) && (z < isize_radius + 1) {
-                break;
-            }
+        let r = self.radius;
+        let count = 3 * r * (r + 1) + 1;
+        self.nth_cell(rng.gen_range(0, count))
+    }
+
+    fn normalise(&self, v: HexagonVector) -> HexagonVector {
+        if self.topology == Topology::Bounded {
+            return v;
         }
+        let origin = HexagonVector { x: 0, y: 0 };
+        let mut v = v;
+        // Step back towards the board through whichever copy of it is nearest.
+        while !self.is_within_bounds(v) {
+            v = self.mirror_centres()
+                .iter()
+                .map(|m| {
+                         HexagonVector {
+                             x: v.x - m.x,
+                             y: v.y - m.y,
+                         }
+                     })
+                .min_by_key(|w| origin.distance(w))
+                .unwrap();
+        }
+        v
+    }
 
-        HexagonVector { x: x, y: y }
+    fn distance(&self, a: &HexagonVector, b: &HexagonVector) -> usize {
+        let direct = a.distance(b);
+        if self.topology == Topology::Bounded {
+            return direct;
+        }
+        self.mirror_centres()
+            .iter()
+            .map(|m| {
+                     a.distance(&HexagonVector {
+                                     x: b.x + m.x,
+                                     y: b.y + m.y,
+                                 })
+                 })
+            .fold(direct, |shortest, d| if d < shortest { d } else { shortest })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::sampling;
     use quickcheck::{Gen, Arbitrary, quickcheck};
     use super::*;
     use rand::OsRng;
@@ -169,7 +261,15 @@ mod tests {
             if radius == 0 {
                 radius = 1;
             }
-            return HexagonGrid { radius: radius };
+            let topology = if Arbitrary::arbitrary(g) {
+                Topology::Toroidal
+            } else {
+                Topology::Bounded
+            };
+            return HexagonGrid {
+                       radius: radius,
+                       topology: topology,
+                   };
         }
     }
 
@@ -225,4 +325,68 @@ mod tests {
     fn random_cells_within_bounds() {
         quickcheck(random_cells_within_bounds_prop as fn(HexagonGrid) -> bool);
     }
+
+    fn inset_shrinks_bounds_prop(g: HexagonGrid, v: HexagonVector, inset: u8) -> bool {
+        let inset = inset as usize;
+        g.is_within_inset(v, 0) == g.is_within_bounds(v) &&
+        (!g.is_within_inset(v, inset + 1) || g.is_within_inset(v, inset))
+    }
+
+    #[test]
+    fn inset_shrinks_bounds() {
+        quickcheck(inset_shrinks_bounds_prop as fn(HexagonGrid, HexagonVector, u8) -> bool);
+    }
+
+    #[test]
+    fn random_cells_are_uniform() {
+        assert!(sampling::is_uniform(&HexagonGrid::new(4)));
+    }
+
+    #[test]
+    fn every_cell_is_reachable() {
+        for radius in 0..5 {
+            assert!(sampling::reaches_every_cell(&HexagonGrid::new(radius)));
+        }
+    }
+
+    fn toroidal_neighbours_within_bounds_prop(radius: usize, d: HexagonDirection) -> bool {
+        let g = HexagonGrid::toroidal(radius);
+        let mut osrng = OsRng::new().unwrap();
+        for _ in 0..100 {
+            let cell = g.random_cell(&mut osrng);
+            if !g.is_within_bounds(g.neighbour(&cell, &d)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    #[test]
+    fn toroidal_neighbours_within_bounds() {
+        quickcheck(toroidal_neighbours_within_bounds_prop as fn(usize, HexagonDirection) -> bool);
+    }
+
+    fn toroidal_neighbour_adjacency_prop(radius: usize, d: HexagonDirection) -> bool {
+        let g = HexagonGrid::toroidal(radius + 1);
+        let mut osrng = OsRng::new().unwrap();
+        let cell = g.random_cell(&mut osrng);
+        g.distance(&cell, &g.neighbour(&cell, &d)) == 1
+    }
+
+    #[test]
+    fn toroidal_neighbour_adjacency() {
+        quickcheck(toroidal_neighbour_adjacency_prop as fn(usize, HexagonDirection) -> bool);
+    }
+
+    fn toroidal_distance_never_exceeds_plain_prop(g: HexagonGrid) -> bool {
+        let mut osrng = OsRng::new().unwrap();
+        let a = g.random_cell(&mut osrng);
+        let b = g.random_cell(&mut osrng);
+        g.distance(&a, &b) <= a.distance(&b) && g.distance(&a, &b) == g.distance(&b, &a)
+    }
+
+    #[test]
+    fn toroidal_distance_never_exceeds_plain() {
+        quickcheck(toroidal_distance_never_exceeds_plain_prop as fn(HexagonGrid) -> bool);
+    }
 }
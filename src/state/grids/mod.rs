@@ -2,6 +2,8 @@ mod traits;
 mod square;
 mod hexagon;
 mod triangle;
+#[cfg(test)]
+mod sampling;
 
 pub use self::traits::*;
 pub use self::hexagon::*;
@@ -0,0 +1,71 @@
+//! Checks on `GridTrait::random_cell` shared by the tests of every tiling.
+
+use rand::{XorShiftRng, SeedableRng};
+use std::hash::Hash;
+use std::collections::{HashMap, HashSet};
+
+use super::traits::*;
+
+const SAMPLES_PER_CELL: usize = 200;
+
+fn rng() -> XorShiftRng {
+    XorShiftRng::from_seed([0x193a6754, 0xa8a7d469, 0x97830e05, 0x113ba7bb])
+}
+
+fn sample<G>(grid: &G, samples: usize) -> HashMap<G::Vector, usize>
+    where G: GridTrait,
+          G::Vector: Hash
+{
+    let mut rng = rng();
+    let mut counts = HashMap::new();
+    for _ in 0..samples {
+        *counts.entry(grid.random_cell(&mut rng)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Pearson's chi-squared test of `random_cell` against the uniform distribution over
+/// `cells`, at a 0.1% significance level.
+pub fn is_uniform<G>(grid: &G) -> bool
+    where G: GridTrait,
+          G::Vector: Hash
+{
+    let cells = grid.cells();
+    let counts = sample(grid, cells.len() * SAMPLES_PER_CELL);
+    if counts.keys().any(|cell| !cells.contains(cell)) {
+        return false;
+    }
+
+    let expected = SAMPLES_PER_CELL as f64;
+    let statistic: f64 = cells
+        .iter()
+        .map(|cell| {
+                 let observed = *counts.get(cell).unwrap_or(&0) as f64;
+                 (observed - expected).powi(2) / expected
+             })
+        .sum();
+    statistic < chi_squared_critical_value(cells.len() - 1)
+}
+
+/// Whether `cells` lists each in-bounds cell exactly once and `random_cell` draws all of them.
+pub fn reaches_every_cell<G>(grid: &G) -> bool
+    where G: GridTrait,
+          G::Vector: Hash
+{
+    let cells = grid.cells();
+    let distinct: HashSet<G::Vector> = cells.iter().cloned().collect();
+    if distinct.len() != cells.len() || !cells.iter().all(|&cell| grid.is_within_bounds(cell)) {
+        return false;
+    }
+
+    let counts = sample(grid, cells.len() * SAMPLES_PER_CELL);
+    counts.len() == cells.len() && counts.keys().all(|cell| distinct.contains(cell))
+}
+
+// The Wilson-Hilferty approximation of the 99.9th percentile of the chi-squared
+// distribution. Close enough for the tens of degrees of freedom tested here.
+fn chi_squared_critical_value(degrees_of_freedom: usize) -> f64 {
+    let k = degrees_of_freedom.max(1) as f64;
+    let z = 3.090;
+    k * (1.0 - 2.0 / (9.0 * k) + z * (2.0 / (9.0 * k)).sqrt()).powi(3)
+}
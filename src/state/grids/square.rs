@@ -77,6 +77,8 @@ impl VectorTrait for SquareVector {
 pub struct SquareGrid {
     pub width: usize,
     pub height: usize,
+    #[serde(default)]
+    pub topology: Topology,
 }
 
 impl SquareGrid {
@@ -85,10 +87,35 @@ impl SquareGrid {
         SquareGrid {
             width: width,
             height: height,
+            topology: Topology::Bounded,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn toroidal(width: usize, height: usize) -> SquareGrid {
+        SquareGrid {
+            width: width,
+            height: height,
+            topology: Topology::Toroidal,
         }
     }
 }
 
+// Distance along one axis of length `size`, optionally wrapping around.
+fn axis_distance(a: isize, b: isize, size: isize, wraps: bool) -> usize {
+    let direct = (a - b).abs();
+    if wraps {
+        let direct = direct % size;
+        (if direct * 2 > size { size - direct } else { direct }) as usize
+    } else {
+        direct as usize
+    }
+}
+
+fn wrap(a: isize, size: isize) -> isize {
+    ((a % size) + size) % size
+}
+
 impl GridTrait for SquareGrid {
     type Vector = SquareVector;
 
@@ -100,8 +127,20 @@ impl GridTrait for SquareGrid {
         v.x >= 0 && v.x < (self.width as isize) && v.y >= 0 && v.y < (self.height as isize)
     }
 
+    fn is_within_inset(&self, v: SquareVector, inset: usize) -> bool {
+        let inset = inset as isize;
+        v.x >= inset && v.x < (self.width as isize) - inset && v.y >= inset &&
+        v.y < (self.height as isize) - inset
+    }
+
     fn cells(&self) -> Vec<SquareVector> {
-        unimplemented!();
+        let mut cells = Vec::with_capacity(self.width * self.height);
+        for x in 0..self.width as isize {
+            for y in 0..self.height as isize {
+                cells.push(SquareVector { x: x, y: y });
+            }
+        }
+        cells
     }
 
     fn random_cell<R: Rng>(&self, rng: &mut R) -> SquareVector {
@@ -112,10 +151,29 @@ impl GridTrait for SquareGrid {
             y: rng.gen_range(0, isize_height),
         }
     }
+
+    fn normalise(&self, v: SquareVector) -> SquareVector {
+        match self.topology {
+            Topology::Bounded => v,
+            Topology::Toroidal => {
+                SquareVector {
+                    x: wrap(v.x, self.width as isize),
+                    y: wrap(v.y, self.height as isize),
+                }
+            }
+        }
+    }
+
+    fn distance(&self, a: &SquareVector, b: &SquareVector) -> usize {
+        let wraps = self.topology == Topology::Toroidal;
+        axis_distance(a.x, b.x, self.width as isize, wraps) +
+        axis_distance(a.y, b.y, self.height as isize, wraps)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::sampling;
     use quickcheck::{Gen, Arbitrary, quickcheck};
     use super::*;
     use rand::OsRng;
@@ -143,9 +201,15 @@ mod tests {
             if height == 0 {
                 height = 1;
             }
+            let topology = if Arbitrary::arbitrary(g) {
+                Topology::Toroidal
+            } else {
+                Topology::Bounded
+            };
             return SquareGrid {
                        width: width,
                        height: height,
+                       topology: topology,
                    };
         }
     }
@@ -202,4 +266,69 @@ mod tests {
     fn random_cells_within_bounds() {
         quickcheck(random_cells_within_bounds_prop as fn(SquareGrid) -> bool);
     }
+
+    fn inset_shrinks_bounds_prop(g: SquareGrid, v: SquareVector, inset: u8) -> bool {
+        let inset = inset as usize;
+        g.is_within_inset(v, 0) == g.is_within_bounds(v) &&
+        (!g.is_within_inset(v, inset + 1) || g.is_within_inset(v, inset))
+    }
+
+    #[test]
+    fn inset_shrinks_bounds() {
+        quickcheck(inset_shrinks_bounds_prop as fn(SquareGrid, SquareVector, u8) -> bool);
+    }
+
+    #[test]
+    fn random_cells_are_uniform() {
+        assert!(sampling::is_uniform(&SquareGrid::new(7, 5)));
+    }
+
+    #[test]
+    fn every_cell_is_reachable() {
+        for width in 1..5 {
+            assert!(sampling::reaches_every_cell(&SquareGrid::new(width, 6 - width)));
+        }
+    }
+
+    fn toroidal_neighbours_within_bounds_prop(width: usize, height: usize, d: SquareDirection) -> bool {
+        let g = SquareGrid::toroidal(width + 1, height + 1);
+        let mut osrng = OsRng::new().unwrap();
+        for _ in 0..100 {
+            let cell = g.random_cell(&mut osrng);
+            if !g.is_within_bounds(g.neighbour(&cell, &d)) {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    #[test]
+    fn toroidal_neighbours_within_bounds() {
+        quickcheck(toroidal_neighbours_within_bounds_prop as
+                   fn(usize, usize, SquareDirection) -> bool);
+    }
+
+    fn toroidal_neighbour_adjacency_prop(width: usize, height: usize, d: SquareDirection) -> bool {
+        let g = SquareGrid::toroidal(width + 2, height + 2);
+        let mut osrng = OsRng::new().unwrap();
+        let cell = g.random_cell(&mut osrng);
+        g.distance(&cell, &g.neighbour(&cell, &d)) == 1
+    }
+
+    #[test]
+    fn toroidal_neighbour_adjacency() {
+        quickcheck(toroidal_neighbour_adjacency_prop as fn(usize, usize, SquareDirection) -> bool);
+    }
+
+    fn toroidal_distance_never_exceeds_plain_prop(g: SquareGrid) -> bool {
+        let mut osrng = OsRng::new().unwrap();
+        let a = g.random_cell(&mut osrng);
+        let b = g.random_cell(&mut osrng);
+        g.distance(&a, &b) <= a.distance(&b) && g.distance(&a, &b) == g.distance(&b, &a)
+    }
+
+    #[test]
+    fn toroidal_distance_never_exceeds_plain() {
+        quickcheck(toroidal_distance_never_exceeds_plain_prop as fn(SquareGrid) -> bool);
+    }
 }
@@ -26,8 +26,43 @@ pub trait GridTrait
 
     fn dimensions(&self) -> Vec<isize>;
     fn is_within_bounds(&self, v: Self::Vector) -> bool;
+    /// Whether `v` is within the grid once `inset` rings have been removed from its edge.
+    fn is_within_inset(&self, v: Self::Vector, inset: usize) -> bool;
     fn cells(&self) -> Vec<Self::Vector>;
     fn random_cell<R: Rng>(&self, rng: &mut R) -> Self::Vector;
+
+    /// Maps a cell which has left a wrap-around grid back onto the board.
+    /// Bounded grids leave cells unchanged.
+    fn normalise(&self, v: Self::Vector) -> Self::Vector {
+        v
+    }
+
+    /// The fewest steps between two cells on this grid, which may be fewer than
+    /// `VectorTrait::distance` if the grid wraps around.
+    fn distance(&self, a: &Self::Vector, b: &Self::Vector) -> usize {
+        a.distance(b)
+    }
+
+    fn neighbour(&self,
+                 v: &Self::Vector,
+                 direction: &<Self::Vector as VectorTrait>::Direction)
+                 -> Self::Vector {
+        self.normalise(v.neighbour(direction))
+    }
+}
+
+/// Whether leaving the board is fatal or brings a snake back in on the opposite side.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topology {
+    Bounded,
+    Toroidal,
+}
+
+impl Default for Topology {
+    fn default() -> Topology {
+        Topology::Bounded
+    }
 }
 
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
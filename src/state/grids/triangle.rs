@@ -122,17 +122,52 @@ impl GridTrait for TriangleGrid {
             .distance(&v) <= self.radius
     }
 
+    fn is_within_inset(&self, v: TriangleVector, inset: usize) -> bool {
+        inset <= self.radius &&
+        TriangleVector {
+                u: 0,
+                v: 0,
+                r: false,
+            }
+            .distance(&v) <= self.radius - inset
+    }
+
     fn cells(&self) -> Vec<TriangleVector> {
-        unimplemented!();
+        let radius = self.radius as isize;
+        let mut cells = Vec::new();
+        for u in -radius..radius + 1 {
+            for v in -radius..radius + 1 {
+                for &r in &[false, true] {
+                    let cell = TriangleVector { u: u, v: v, r: r };
+                    if self.is_within_bounds(cell) {
+                        cells.push(cell);
+                    }
+                }
+            }
+        }
+        cells
     }
 
-    fn random_cell<R: Rng>(&self, _: &mut R) -> TriangleVector {
-        unimplemented!();
+    fn random_cell<R: Rng>(&self, rng: &mut R) -> TriangleVector {
+        // Every cell lies within `radius` of the origin along both axes, so drawing from
+        // that box until a cell falls within bounds picks each one equally often.
+        let radius = self.radius as isize;
+        loop {
+            let cell = TriangleVector {
+                u: rng.gen_range(-radius, radius + 1),
+                v: rng.gen_range(-radius, radius + 1),
+                r: rng.gen(),
+            };
+            if self.is_within_bounds(cell) {
+                return cell;
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::super::sampling;
     use quickcheck::{Gen, Arbitrary, quickcheck};
     use super::*;
     use rand::OsRng;
@@ -206,8 +241,20 @@ mod tests {
         return true;
     }
 
-    //#[test]
+    #[test]
     fn random_cells_within_bounds() {
         quickcheck(random_cells_within_bounds_prop as fn(TriangleGrid) -> bool);
     }
+
+    #[test]
+    fn random_cells_are_uniform() {
+        assert!(sampling::is_uniform(&TriangleGrid::new(4)));
+    }
+
+    #[test]
+    fn every_cell_is_reachable() {
+        for radius in 0..5 {
+            assert!(sampling::reaches_every_cell(&TriangleGrid::new(radius)));
+        }
+    }
 }
@@ -0,0 +1,32 @@
+use super::*;
+
+/// Special food, picked up by moving a snake's head onto it. Plain food is kept separately
+/// in `RoundState::food`.
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub position: Vector,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    /// Grows the snake by several segments.
+    GoldenFood,
+    /// Removes segments from the end of the snake.
+    ShrinkPill,
+    /// Protects the snake from colliding with other snakes for a while.
+    Invulnerability,
+    /// Moves the snake two steps per round for a while.
+    SpeedBoost,
+}
+
+impl ItemKind {
+    pub fn variants() -> &'static [ItemKind] {
+        static VARIANTS: &'static [ItemKind] = &[ItemKind::GoldenFood,
+                                                 ItemKind::ShrinkPill,
+                                                 ItemKind::Invulnerability,
+                                                 ItemKind::SpeedBoost];
+        VARIANTS
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::collections::HashSet;
+use serde_json;
+
+use super::*;
+use super::grids::*;
+use errors::*;
+
+/// Terrain laid over a grid. Walls are lethal cells on the board; holes are cells cut out
+/// of the board, which are treated just like leaving its bounds.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Map {
+    #[serde(default)]
+    pub walls: HashSet<Vector>,
+    #[serde(default)]
+    pub holes: HashSet<Vector>,
+}
+
+impl Map {
+    /// Loads a map from JSON if the file name ends in `.json`, or else from the text format
+    /// read by `from_text`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Map> {
+        let is_json = path.as_ref()
+            .extension()
+            .map_or(false, |extension| extension == "json");
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .chain_err(|| "reading map file")?;
+        if is_json {
+            Map::from_json(&contents)
+        } else {
+            Map::from_text(&contents)
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Map> {
+        serde_json::from_str(json).chain_err(|| "parsing map file")
+    }
+
+    /// Parses one cell per line, as `wall` or `hole` followed by the cell in JSON:
+    ///
+    /// ```text
+    /// # Comments and blank lines are ignored.
+    /// wall {"x": 3, "y": -1}
+    /// hole {"x": 0, "y": 0}
+    /// ```
+    pub fn from_text(text: &str) -> Result<Map> {
+        let mut map = Map::default();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (kind, cell) = match line.find(char::is_whitespace) {
+                Some(n) => line.split_at(n),
+                None => {
+                    let reason = "expected `wall` or `hole` followed by a cell".to_string();
+                    bail!(ErrorKind::InvalidMap(line_number, reason))
+                }
+            };
+            let cell: Vector = serde_json::from_str(cell.trim())
+                .chain_err(|| ErrorKind::InvalidMap(line_number, "invalid cell".to_string()))?;
+            match kind {
+                "wall" => map.walls.insert(cell),
+                "hole" => map.holes.insert(cell),
+                _ => {
+                    let reason = format!("unknown cell kind `{}`", kind);
+                    bail!(ErrorKind::InvalidMap(line_number, reason))
+                }
+            };
+        }
+        Ok(map)
+    }
+
+    pub fn is_wall(&self, v: &Vector) -> bool {
+        self.walls.contains(v)
+    }
+
+    /// Whether `v` is part of the board: within the grid's bounds and not a hole.
+    pub fn is_on_board(&self, grid: &Grid, v: &Vector) -> bool {
+        grid.is_within_bounds(*v) && !self.holes.contains(v)
+    }
+
+    /// Whether a snake or food could occupy `v`.
+    pub fn is_open(&self, grid: &Grid, v: &Vector) -> bool {
+        self.is_on_board(grid, v) && !self.is_wall(v)
+    }
+}
+
+// Cells are written as `x`/`y` pairs, which triangle grids don't use.
+#[cfg(all(test, not(feature = "triangle")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_and_json_maps_agree() {
+        let wall: Vector = serde_json::from_str(r#"{"x": 1, "y": 2}"#).unwrap();
+        let hole: Vector = serde_json::from_str(r#"{"x": 0, "y": 0}"#).unwrap();
+
+        let text = "# A wall and a hole.\n\nwall {\"x\": 1, \"y\": 2}\nhole {\"x\": 0, \"y\": 0}\n";
+        let from_text = Map::from_text(text).unwrap();
+        assert!(from_text.is_wall(&wall));
+        assert!(from_text.holes.contains(&hole));
+
+        let json = r#"{"walls": [{"x": 1, "y": 2}], "holes": [{"x": 0, "y": 0}]}"#;
+        assert_eq!(Map::from_json(json).unwrap(), from_text);
+    }
+
+    #[test]
+    fn unknown_cell_kinds_are_rejected() {
+        assert!(Map::from_text("lava {\"x\": 1, \"y\": 2}").is_err());
+        assert!(Map::from_text("wall").is_err());
+    }
+}
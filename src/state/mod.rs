@@ -1,9 +1,19 @@
 mod game;
 mod snake;
+mod map;
+mod rules;
+mod occupancy;
+mod items;
+mod stats;
 pub mod grids;
 
 pub use self::game::*;
 pub use self::snake::*;
+pub use self::map::*;
+pub use self::rules::*;
+pub use self::occupancy::*;
+pub use self::items::*;
+pub use self::stats::*;
 use self::grids::*;
 pub use self::grids::GridEnum;
 
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use super::*;
+
+/// Which snakes cover each cell, so that collisions and free cells can be found without
+/// scanning every snake. Usually one owner per cell; more during a collision.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Occupancy {
+    cells: HashMap<Vector, Vec<String>, BuildHasherDefault<CellHasher>>,
+}
+
+// Cells are hashed on every step of every snake, and are never chosen by clients, so a
+// simple multiplicative hash does instead of the default SipHash.
+#[derive(Default)]
+struct CellHasher {
+    hash: u64,
+}
+
+impl Hasher for CellHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(byte as u64);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ n).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+impl Occupancy {
+    pub fn from_snakes(snakes: &HashMap<String, Snake>) -> Occupancy {
+        let mut occupancy = Occupancy::default();
+        for (name, snake) in snakes {
+            occupancy.insert_snake(name, snake);
+        }
+        occupancy
+    }
+
+    pub fn insert(&mut self, v: Vector, owner: &str) {
+        self.cells
+            .entry(v)
+            .or_insert_with(Vec::new)
+            .push(owner.to_string());
+    }
+
+    /// Removes one of `owner`'s segments from `v`.
+    pub fn remove(&mut self, v: &Vector, owner: &str) {
+        let now_empty = match self.cells.get_mut(v) {
+            Some(owners) => {
+                if let Some(index) = owners.iter().position(|o| o == owner) {
+                    owners.swap_remove(index);
+                }
+                owners.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            self.cells.remove(v);
+        }
+    }
+
+    pub fn insert_snake(&mut self, owner: &str, snake: &Snake) {
+        for segment in &snake.segments {
+            self.insert(*segment, owner);
+        }
+    }
+
+    /// Moves one of `owner`'s segments from `from` to `to`, as a snake stepping forward
+    /// does. Cheaper than a `remove` and an `insert`, as it reuses what it can.
+    pub fn shift(&mut self, from: &Vector, to: Vector, owner: &str) {
+        let mut spare = None;
+        let mut moved = None;
+        if let Some(mut owners) = self.cells.remove(from) {
+            if let Some(index) = owners.iter().position(|o| o == owner) {
+                moved = Some(owners.swap_remove(index));
+            }
+            if owners.is_empty() {
+                spare = Some(owners);
+            } else {
+                self.cells.insert(*from, owners);
+            }
+        }
+        let moved = moved.unwrap_or_else(|| owner.to_string());
+        match self.cells.entry(to) {
+            Entry::Occupied(mut entry) => entry.get_mut().push(moved),
+            Entry::Vacant(entry) => {
+                let mut owners = spare.unwrap_or_else(Vec::new);
+                owners.push(moved);
+                entry.insert(owners);
+            }
+        }
+    }
+
+    pub fn remove_snake(&mut self, owner: &str, snake: &Snake) {
+        for segment in &snake.segments {
+            self.remove(segment, owner);
+        }
+    }
+
+    pub fn is_occupied(&self, v: &Vector) -> bool {
+        self.cells.contains_key(v)
+    }
+
+    pub fn owners(&self, v: &Vector) -> &[String] {
+        self.cells.get(v).map_or(&[], |owners| owners.as_slice())
+    }
+
+    /// How many of `owner`'s segments lie on `v`.
+    pub fn count(&self, v: &Vector, owner: &str) -> usize {
+        self.owners(v).iter().filter(|&o| o == owner).count()
+    }
+}
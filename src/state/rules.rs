@@ -0,0 +1,317 @@
+use std::fs::File;
+use std::path::Path;
+use serde_json;
+
+use super::*;
+use errors::*;
+
+/// Settings for a game which are fixed before it starts. Every field may be omitted from
+/// a rules file to keep its default.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub spawns: SpawnRules,
+    #[serde(default)]
+    pub food: FoodRules,
+    #[serde(default)]
+    pub items: ItemRules,
+    #[serde(default)]
+    pub head_on: HeadOnPolicy,
+    #[serde(default)]
+    pub sudden_death: Option<SuddenDeath>,
+    /// Whether snakes on the same team pass through each other instead of colliding.
+    #[serde(default)]
+    pub teammates_pass_through: bool,
+    /// Whether players may join a running game. They spawn between rounds.
+    #[serde(default)]
+    pub join_mid_game: bool,
+    #[serde(default)]
+    pub arena: Option<ArenaRules>,
+}
+
+impl Rules {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Rules> {
+        let file = File::open(path).chain_err(|| "opening rules file")?;
+        serde_json::from_reader(file).chain_err(|| "parsing rules file")
+    }
+
+    /// Arenas never end, so players can always join them.
+    pub fn allows_joining_mid_game(&self) -> bool {
+        self.join_mid_game || self.arena.is_some()
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    Classic,
+    /// Light cycles: snakes grow with every step and there is no food or items.
+    Tron,
+}
+
+impl Default for GameMode {
+    fn default() -> GameMode {
+        GameMode::Classic
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpawnRules {
+    #[serde(default)]
+    pub strategy: SpawnStrategy,
+    #[serde(default = "default_initial_length")]
+    pub initial_length: usize,
+    #[serde(default)]
+    pub facing: Facing,
+}
+
+impl Default for SpawnRules {
+    fn default() -> SpawnRules {
+        SpawnRules {
+            strategy: SpawnStrategy::default(),
+            initial_length: default_initial_length(),
+            facing: Facing::default(),
+        }
+    }
+}
+
+fn default_initial_length() -> usize {
+    1
+}
+
+/// Where new snakes are placed. Snakes never spawn on top of food, walls or other snakes.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnStrategy {
+    /// Any free cell.
+    Random,
+    /// The free cell furthest from every other snake.
+    Spaced,
+}
+
+impl Default for SpawnStrategy {
+    fn default() -> SpawnStrategy {
+        SpawnStrategy::Spaced
+    }
+}
+
+/// Which way new snakes face. The body trails behind the head, and snakes are never
+/// placed facing straight into a wall or off the board.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Facing {
+    Random,
+    Fixed(Direction),
+}
+
+impl Default for Facing {
+    fn default() -> Facing {
+        Facing::Random
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoodRules {
+    /// How many foods the board holds at once, counting food left by dead snakes.
+    #[serde(default = "default_food_count")]
+    pub count: usize,
+    /// The chance each round of replacing each missing food.
+    #[serde(default = "default_spawn_chance")]
+    pub spawn_chance: f64,
+    #[serde(default)]
+    pub placement: FoodPlacement,
+    /// Food from dead snakes disappears after this many rounds, if set.
+    #[serde(default)]
+    pub corpse_decay: Option<usize>,
+    /// How many segments a snake gains from each food, one per round.
+    #[serde(default = "default_food_growth")]
+    pub growth: usize,
+}
+
+impl Default for FoodRules {
+    fn default() -> FoodRules {
+        FoodRules {
+            count: default_food_count(),
+            spawn_chance: default_spawn_chance(),
+            placement: FoodPlacement::default(),
+            corpse_decay: None,
+            growth: default_food_growth(),
+        }
+    }
+}
+
+fn default_food_count() -> usize {
+    1
+}
+
+fn default_spawn_chance() -> f64 {
+    1.0
+}
+
+fn default_food_growth() -> usize {
+    1
+}
+
+/// Where new food is placed.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FoodPlacement {
+    /// Any free cell.
+    Uniform,
+    /// Any free cell, but more likely the further it is from the nearest snake's head.
+    AwayFromHeads,
+}
+
+impl Default for FoodPlacement {
+    fn default() -> FoodPlacement {
+        FoodPlacement::Uniform
+    }
+}
+
+/// What happens when snakes move their heads onto the same cell. A snake whose head lands
+/// on another snake's body always dies.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadOnPolicy {
+    BothDie,
+    /// The longest snake survives. If several are longest, they all die.
+    LongerSurvives,
+    /// Every snake involved is put back where it was before moving.
+    Bounce,
+}
+
+impl Default for HeadOnPolicy {
+    fn default() -> HeadOnPolicy {
+        HeadOnPolicy::BothDie
+    }
+}
+
+/// Shrinks the arena to break stalemates: after `start_round`, one ring is removed from the
+/// edge of the grid every `interval` rounds.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SuddenDeath {
+    pub start_round: usize,
+    pub interval: usize,
+}
+
+impl SuddenDeath {
+    /// How many rings have been removed by `round_number`.
+    pub fn inset(&self, round_number: usize) -> usize {
+        if round_number <= self.start_round {
+            return 0;
+        }
+        (round_number - self.start_round - 1) / self.interval.max(1) + 1
+    }
+}
+
+/// Special food. None is placed unless `count` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemRules {
+    /// How many items the board holds at once.
+    #[serde(default)]
+    pub count: usize,
+    /// How likely each kind of item is to be placed, relative to the others.
+    #[serde(default)]
+    pub weights: ItemWeights,
+    #[serde(default = "default_golden_growth")]
+    pub golden_growth: usize,
+    /// Snakes never shrink below one segment.
+    #[serde(default = "default_shrink_by")]
+    pub shrink_by: usize,
+    /// How many rounds invulnerability and speed boosts last, including the one they are
+    /// picked up in.
+    #[serde(default = "default_effect_rounds")]
+    pub effect_rounds: usize,
+}
+
+impl Default for ItemRules {
+    fn default() -> ItemRules {
+        ItemRules {
+            count: 0,
+            weights: ItemWeights::default(),
+            golden_growth: default_golden_growth(),
+            shrink_by: default_shrink_by(),
+            effect_rounds: default_effect_rounds(),
+        }
+    }
+}
+
+fn default_golden_growth() -> usize {
+    3
+}
+
+fn default_shrink_by() -> usize {
+    2
+}
+
+fn default_effect_rounds() -> usize {
+    5
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemWeights {
+    #[serde(default = "default_item_weight")]
+    pub golden_food: u32,
+    #[serde(default = "default_item_weight")]
+    pub shrink_pill: u32,
+    #[serde(default = "default_item_weight")]
+    pub invulnerability: u32,
+    #[serde(default = "default_item_weight")]
+    pub speed_boost: u32,
+}
+
+impl ItemWeights {
+    pub fn weight(&self, kind: ItemKind) -> u32 {
+        match kind {
+            ItemKind::GoldenFood => self.golden_food,
+            ItemKind::ShrinkPill => self.shrink_pill,
+            ItemKind::Invulnerability => self.invulnerability,
+            ItemKind::SpeedBoost => self.speed_boost,
+        }
+    }
+}
+
+impl Default for ItemWeights {
+    fn default() -> ItemWeights {
+        ItemWeights {
+            golden_food: default_item_weight(),
+            shrink_pill: default_item_weight(),
+            invulnerability: default_item_weight(),
+            speed_boost: default_item_weight(),
+        }
+    }
+}
+
+fn default_item_weight() -> u32 {
+    1
+}
+
+/// Makes a game an endless arena: it never concludes, and dead snakes respawn.
+#[derive(PartialEq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ArenaRules {
+    /// How many rounds a dead player waits before respawning.
+    #[serde(default = "default_respawn_rounds")]
+    pub respawn_rounds: usize,
+    /// How often the leaderboard is sent out, in seconds.
+    #[serde(default = "default_leaderboard_secs")]
+    pub leaderboard_secs: u64,
+}
+
+impl Default for ArenaRules {
+    fn default() -> ArenaRules {
+        ArenaRules {
+            respawn_rounds: default_respawn_rounds(),
+            leaderboard_secs: default_leaderboard_secs(),
+        }
+    }
+}
+
+fn default_respawn_rounds() -> usize {
+    10
+}
+
+fn default_leaderboard_secs() -> u64 {
+    60
+}
@@ -3,17 +3,26 @@ use super::grids::*;
 
 #[derive(PartialEq, Eq, Clone, Hash, Debug, Serialize, Deserialize)]
 pub struct Snake {
-    // Could utilise BTreeSet (ordered set) instead of Vec. Cheaply optimise collision detecting?
+    // The engine finds collisions through `RoundState::occupancy` rather than these.
     pub segments: Vec<Vector>,
-    #[serde(skip_serializing, skip_deserializing)]
-    previous_tail: Option<Vector>,
+    /// Segments still to be added. The tail stays put for this many more steps.
+    #[serde(default)]
+    pub growth: usize,
+    /// Rounds left, including this one, that the snake can't collide with other snakes.
+    #[serde(default)]
+    pub invulnerable: usize,
+    /// Rounds left, including this one, that the snake moves two steps per round.
+    #[serde(default)]
+    pub boosted: usize,
 }
 
 impl Snake {
     pub fn new(segments: Vec<Vector>) -> Snake {
         Snake {
             segments: segments,
-            previous_tail: None,
+            growth: 0,
+            invulnerable: 0,
+            boosted: 0,
         }
     }
 
@@ -25,33 +34,56 @@ impl Snake {
         self.segments.iter().any(|x| x == v)
     }
 
-    pub fn has_collided_into(&self, other: &Snake) -> bool {
+    pub fn has_collided_into(&self, other: &Snake, grid: &Grid) -> bool {
+        // Segment `i` is `i` steps from the other head, so it can't be our head unless our
+        // head is at most `i` steps away. The grid's distance allows for wrap-around.
         let my_head = self.segments[0];
-        let mut next_candidate = my_head.distance(&other.segments[0]);
+        let mut next_candidate = grid.distance(&my_head, &other.segments[0]);
         while let Some(here) = other.segments.get(next_candidate) {
             if my_head == *here {
                 return true;
             }
-            next_candidate += my_head.distance(here);
+            next_candidate += grid.distance(&my_head, here);
         }
         false
     }
 
     pub fn step_in_direction(&mut self, dir: Direction) {
-        self.previous_tail = self.segments.last().cloned();
-        if !self.segments.is_empty() {
-            for i in (1..self.segments.len()).rev() {
-                self.segments[i] = self.segments[i - 1];
-            }
-            self.segments[0] = self.segments[0].neighbour(&dir);
+        if let Some(head) = self.segments.first().map(|head| head.neighbour(&dir)) {
+            self.step_to(head);
+        }
+    }
+
+    /// Moves the head onto `head`, which should be adjacent to the current head. Returns
+    /// the cell the tail left, unless the snake grew instead.
+    pub fn step_to(&mut self, head: Vector) -> Option<Vector> {
+        if self.segments.is_empty() {
+            return None;
         }
+        let vacated = if self.growth > 0 {
+            self.growth -= 1;
+            None
+        } else {
+            self.segments.pop()
+        };
+        self.segments.insert(0, head);
+        vacated
     }
 
-    pub fn grow(&mut self) {
-        if let Some(previous_tail) = self.previous_tail {
-            self.segments.push(previous_tail);
-            self.previous_tail = None;
+    /// Removes up to `segments` from the tail, keeping at least the head. Returns the cells
+    /// left behind.
+    pub fn shrink(&mut self, segments: usize) -> Vec<Vector> {
+        let length = self.segments.len();
+        let keep = if length > segments { length - segments } else { 1 };
+        if keep >= length {
+            return Vec::new();
         }
+        self.segments.split_off(keep)
+    }
+
+    /// Lengthens the snake by `segments` over its next steps.
+    pub fn grow(&mut self, segments: usize) {
+        self.growth += segments;
     }
 }
 
@@ -63,6 +95,9 @@ pub enum CauseOfDeath {
     NoMoveMade,
     CollidedWithSnake,
     CollidedWithBounds,
+    CollidedWithWall,
+    Forfeit,
+    Disconnected,
 }
 
 #[cfg(test)]
@@ -86,7 +121,9 @@ mod tests {
                 .collect();
             return Snake {
                        segments: segments,
-                       previous_tail: None,
+                       growth: g.gen_range(0, 3),
+                       invulnerable: 0,
+                       boosted: 0,
                    };
         }
 
@@ -95,7 +132,9 @@ mod tests {
             for i in 0..self.segments.len() {
                 shrinks.push(Snake {
                                  segments: self.segments[..i].to_vec(),
-                                 previous_tail: None,
+                                 growth: self.growth,
+                                 invulnerable: 0,
+                                 boosted: 0,
                              })
             }
             return Box::new(shrinks.into_iter());
@@ -126,6 +165,26 @@ mod tests {
         quickcheck(step_preserves_connectedness_prop as fn(Snake, Direction) -> bool);
     }
 
+    fn growth_lengthens_snake_prop(snake: Snake, growth: u8, dirs: Vec<Direction>) -> bool {
+        if snake.segments.is_empty() {
+            return true;
+        }
+        let mut grown = snake.clone();
+        grown.grow(growth as usize);
+        let pending = grown.growth;
+        for dir in &dirs {
+            grown.step_in_direction(*dir);
+        }
+        let added = if pending < dirs.len() { pending } else { dirs.len() };
+        grown.segments.len() == snake.segments.len() + added && grown.growth == pending - added &&
+        snake_is_connected_prop(grown)
+    }
+
+    #[test]
+    fn growth_lengthens_snake() {
+        quickcheck(growth_lengthens_snake_prop as fn(Snake, u8, Vec<Direction>) -> bool);
+    }
+
     fn head_is_at_head_prop(snake: Snake) -> bool {
         snake.segments.len() == 0 || snake.is_head_at(&snake.segments[0])
     }
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// What a player did over a game, reported with its outcome.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub rounds_survived: usize,
+    pub food_eaten: usize,
+    pub max_length: usize,
+    /// How many snakes died running into this player, body or head.
+    pub kills: usize,
+    pub moves_made: usize,
+    /// How many of the player's replies were timed.
+    pub responses: usize,
+    pub average_response_millis: f64,
+}
+
+impl PlayerStats {
+    pub fn record_response(&mut self, elapsed: Duration) {
+        let millis = elapsed.as_secs() as f64 * 1000.0 + elapsed.subsec_nanos() as f64 / 1_000_000.0;
+        self.responses += 1;
+        self.average_response_millis += (millis - self.average_response_millis) /
+                                        self.responses as f64;
+    }
+}
+
+/// The leaders of an endless arena, best first.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Standings {
+    /// Living snakes by their current length.
+    pub longest: Vec<(String, usize)>,
+    /// Players by how many snakes they killed in the last hour.
+    pub most_kills: Vec<(String, usize)>,
+}